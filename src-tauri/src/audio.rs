@@ -17,6 +17,42 @@ pub struct DeviceInfo {
     pub is_input: bool,
 }
 
+/// Which of the `resolve_input_config` strategies produced the config.
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigStrategy {
+    DefaultInput,
+    SupportedInput,
+    DefaultOutput,
+}
+
+/// Result of `probe_device`: what was negotiated and what was heard.
+#[derive(Serialize, Clone)]
+pub struct ProbeReport {
+    pub device_name: String,
+    pub strategy: ConfigStrategy,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: String,
+    pub captured_ms: u64,
+    /// Quietest 50 ms window (RMS), as raw i16 amplitude and dBFS (-96 for silence).
+    pub noise_floor: i16,
+    pub noise_floor_dbfs: f32,
+    /// Largest absolute sample, as raw i16 amplitude and dBFS.
+    pub peak: i16,
+    pub peak_dbfs: f32,
+    pub signal_detected: bool,
+    /// Base64 WAV of the first few seconds of the capture.
+    pub preview: String,
+}
+
+/// Amplitude above which a buffer is considered to contain speech rather than
+/// breaths or background noise.
+const SPEECH_THRESHOLD: i16 = 800;
+/// Reported for digital silence instead of -inf, which JSON can't carry.
+/// Just below the quietest non-zero sample (about -90 dBFS).
+const MIN_DBFS: f32 = -96.0;
+
 pub struct InterviewStreams(pub Arc<Mutex<Option<Vec<cpal::Stream>>>>);
 unsafe impl Send for InterviewStreams {}
 unsafe impl Sync for InterviewStreams {}
@@ -80,7 +116,7 @@ pub fn start_listening(app: AppHandle, speaker_type: String, target_device_name:
     // ──── Resolve input config ────
     // For loopback capture (interviewer), the device may be an output device.
     // On WASAPI, cpal transparently supports build_input_stream on output devices.
-    let config = resolve_input_config(&device, &device_name)?;
+    let (config, _) = resolve_input_config(&device, &device_name)?;
 
     let sample_rate = config.sample_rate().0;
     let channels = config.channels();
@@ -173,11 +209,14 @@ fn clone_device_by_name(host: &cpal::Host, name: &str) -> Option<cpal::Device> {
 /// Determine a workable input configuration for the device.
 /// For output devices (loopback), `default_input_config` may fail, so we
 /// try multiple strategies.
-fn resolve_input_config(device: &cpal::Device, device_name: &str) -> Option<cpal::SupportedStreamConfig> {
+fn resolve_input_config(
+    device: &cpal::Device,
+    device_name: &str,
+) -> Option<(cpal::SupportedStreamConfig, ConfigStrategy)> {
     // Strategy 1: default_input_config (works for most input and some loopback devices)
     if let Ok(config) = device.default_input_config() {
         println!("[Audio] Using default_input_config for '{}'", device_name);
-        return Some(config);
+        return Some((config, ConfigStrategy::DefaultInput));
     }
 
     // Strategy 2: enumerate supported input configs and pick the best one
@@ -203,7 +242,7 @@ fn resolve_input_config(device: &cpal::Device, device_name: &str) -> Option<cpal
                     config.sample_rate().0,
                     config.sample_format()
                 );
-                return Some(config);
+                return Some((config, ConfigStrategy::SupportedInput));
             }
         }
     }
@@ -218,14 +257,15 @@ fn resolve_input_config(device: &cpal::Device, device_name: &str) -> Option<cpal
             output_config.sample_rate().0,
             output_config.sample_format()
         );
-        return Some(output_config);
+        return Some((output_config, ConfigStrategy::DefaultOutput));
     }
 
     eprintln!("[Audio] ✗ No workable config found for '{}'", device_name);
     None
 }
 
-/// Build an input stream that matches the device's sample format.
+/// Build an input stream that feeds the capture buffer and emits `audio-chunk`
+/// events.
 fn build_capture_stream(
     device: &cpal::Device,
     config: &cpal::SupportedStreamConfig,
@@ -236,72 +276,65 @@ fn build_capture_stream(
     speaker: String,
     device_name: &str,
 ) -> Option<cpal::Stream> {
+    build_sample_stream(device, config, device_name, move |data: &[i16]| {
+        crate::transcribe::feed(&app, &speaker, data, sample_rate, channels);
+        let mut b = buffer.lock().unwrap();
+        let mut max_v: i16 = 0;
+        for &s in data {
+            b.push(s);
+            let abs = s.saturating_abs();
+            if abs > max_v { max_v = abs; }
+        }
+        check_and_send_buffer(&mut b, sample_rate, channels, &app, &speaker, max_v);
+    })
+}
+
+/// Build an input stream that matches the device's sample format and hands
+/// every callback's samples to `on_data` as interleaved i16.
+fn build_sample_stream<F>(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    device_name: &str,
+    mut on_data: F,
+) -> Option<cpal::Stream>
+where
+    F: FnMut(&[i16]) + Send + 'static,
+{
     let stream_config: cpal::StreamConfig = config.clone().into();
     let dn = device_name.to_string();
+    let mut converted: Vec<i16> = Vec::new();
 
     let stream = match config.sample_format() {
         cpal::SampleFormat::F32 => {
-            let buf = buffer.clone();
-            let app = app.clone();
-            let spk = speaker.clone();
             device.build_input_stream(
                 &stream_config,
                 move |data: &[f32], _| {
-                    let mut b = buf.lock().unwrap();
-                    let mut max_v: i16 = 0;
-                    for &sample in data {
-                        let converted = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
-                        b.push(converted);
-                        let abs = converted.abs();
-                        if abs > max_v { max_v = abs; }
-                    }
-                    check_and_send_buffer(&mut b, sample_rate, channels, &app, &spk, max_v);
+                    converted.clear();
+                    converted.extend(data.iter().map(|&sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16));
+                    on_data(&converted);
                 },
                 move |e| eprintln!("[Audio] Stream error on '{}': {}", dn, e),
                 None,
             )
         }
         cpal::SampleFormat::I16 => {
-            let buf = buffer.clone();
-            let app = app.clone();
-            let spk = speaker.clone();
-            let dn2 = device_name.to_string();
             device.build_input_stream(
                 &stream_config,
-                move |data: &[i16], _| {
-                    let mut b = buf.lock().unwrap();
-                    let mut max_v: i16 = 0;
-                    for &s in data {
-                        b.push(s);
-                        let abs = s.abs();
-                        if abs > max_v { max_v = abs; }
-                    }
-                    check_and_send_buffer(&mut b, sample_rate, channels, &app, &spk, max_v);
-                },
-                move |e| eprintln!("[Audio] Stream error on '{}': {}", dn2, e),
+                move |data: &[i16], _| on_data(data),
+                move |e| eprintln!("[Audio] Stream error on '{}': {}", dn, e),
                 None,
             )
         }
         cpal::SampleFormat::U16 => {
-            let buf = buffer.clone();
-            let app = app.clone();
-            let spk = speaker.clone();
-            let dn2 = device_name.to_string();
             device.build_input_stream(
                 &stream_config,
                 move |data: &[u16], _| {
-                    let mut b = buf.lock().unwrap();
-                    let mut max_v: i16 = 0;
-                    for &sample in data {
-                        // Convert u16 (0..65535) to i16 (-32768..32767)
-                        let converted = (sample as i32 - 32768) as i16;
-                        b.push(converted);
-                        let abs = converted.abs();
-                        if abs > max_v { max_v = abs; }
-                    }
-                    check_and_send_buffer(&mut b, sample_rate, channels, &app, &spk, max_v);
+                    // Convert u16 (0..65535) to i16 (-32768..32767)
+                    converted.clear();
+                    converted.extend(data.iter().map(|&sample| (sample as i32 - 32768) as i16));
+                    on_data(&converted);
                 },
-                move |e| eprintln!("[Audio] Stream error on '{}': {}", dn2, e),
+                move |e| eprintln!("[Audio] Stream error on '{}': {}", dn, e),
                 None,
             )
         }
@@ -320,6 +353,94 @@ fn build_capture_stream(
     }
 }
 
+/// Open a device exactly the way `start_listening` would, capture for
+/// `duration` and report what was negotiated and how loud the signal was.
+///
+/// Blocks for the whole capture, so callers should run it off the main thread.
+pub fn probe_device(
+    target_device_name: Option<String>,
+    duration: std::time::Duration,
+) -> Result<ProbeReport, String> {
+    let host = cpal::default_host();
+    let device = resolve_device(&host, "interviewer", target_device_name.as_deref())
+        .ok_or("No matching audio device found")?;
+    let device_name = device.name().unwrap_or_else(|_| "unknown".into());
+
+    let (config, strategy) = resolve_input_config(&device, &device_name)
+        .ok_or_else(|| format!("No workable input config for '{}'", device_name))?;
+    let sample_rate = config.sample_rate().0;
+    let channels = config.channels();
+
+    println!(
+        "[Probe] Capturing {:?} from '{}' ({}Hz, {} ch, {:?}, {:?})",
+        duration, device_name, sample_rate, channels, config.sample_format(), strategy
+    );
+
+    let samples = Arc::new(Mutex::new(Vec::<i16>::new()));
+    let sink = samples.clone();
+    let stream = build_sample_stream(&device, &config, &device_name, move |data: &[i16]| {
+        sink.lock().unwrap().extend_from_slice(data);
+    })
+    .ok_or_else(|| format!("Failed to build input stream for '{}'", device_name))?;
+
+    stream.play().map_err(|e| format!("Failed to start stream: {}", e))?;
+    std::thread::sleep(duration);
+    drop(stream);
+
+    let samples = std::mem::take(&mut *samples.lock().unwrap());
+    let frame_len = channels.max(1) as usize;
+    let captured_ms = (samples.len() / frame_len) as u64 * 1000 / sample_rate.max(1) as u64;
+
+    let peak = samples.iter().map(|s| s.saturating_abs()).max().unwrap_or(0);
+    let noise_floor = noise_floor(&samples, sample_rate as usize * frame_len / 20);
+
+    // Keep the preview short — it only needs to prove which source this is.
+    let preview_len = (sample_rate as usize * frame_len * 5).min(samples.len());
+    let preview = general_purpose::STANDARD.encode(create_wav_data(sample_rate, channels, &samples[..preview_len]));
+
+    println!(
+        "[Probe] '{}': {} ms captured, peak {}, noise floor {}",
+        device_name, captured_ms, peak, noise_floor
+    );
+
+    Ok(ProbeReport {
+        device_name,
+        strategy,
+        sample_rate,
+        channels,
+        sample_format: format!("{:?}", config.sample_format()),
+        captured_ms,
+        noise_floor,
+        noise_floor_dbfs: to_dbfs(noise_floor),
+        peak,
+        peak_dbfs: to_dbfs(peak),
+        signal_detected: peak > SPEECH_THRESHOLD,
+        preview,
+    })
+}
+
+/// RMS of the quietest window of `window` samples.
+fn noise_floor(samples: &[i16], window: usize) -> i16 {
+    if samples.is_empty() || window == 0 {
+        return 0;
+    }
+    samples
+        .chunks(window)
+        .map(|w| {
+            let sum: f64 = w.iter().map(|&s| (s as f64) * (s as f64)).sum();
+            (sum / w.len() as f64).sqrt() as i16
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+fn to_dbfs(amplitude: i16) -> f32 {
+    if amplitude <= 0 {
+        return MIN_DBFS;
+    }
+    (20.0 * (amplitude as f32 / i16::MAX as f32).log10()).max(MIN_DBFS)
+}
+
 fn check_and_send_buffer(
     buf: &mut Vec<i16>,
     sample_rate: u32,
@@ -336,7 +457,7 @@ fn check_and_send_buffer(
         let max_amp = pcm_data.iter().map(|x| x.abs()).max().unwrap_or(0);
 
         // Only send to API if there is meaningful speech (amplitude > 800 filters breaths/noise)
-        if max_amp > 800 {
+        if max_amp > SPEECH_THRESHOLD {
             let wav_data = create_wav_data(sample_rate, channels, &pcm_data);
             let b64 = general_purpose::STANDARD.encode(wav_data);
             let _ = app.emit("audio-chunk", AudioPayload {
@@ -382,3 +503,17 @@ fn create_wav_data(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8>
     }
     cursor.into_inner()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dbfs_is_zero_at_full_scale_and_floored_for_silence() {
+        assert_eq!(to_dbfs(i16::MAX), 0.0);
+        assert_eq!(to_dbfs(0), MIN_DBFS);
+        assert!((to_dbfs(i16::MAX / 2) + 6.02).abs() < 0.01);
+        assert!(to_dbfs(1) > MIN_DBFS);
+        assert!(serde_json::to_string(&to_dbfs(0)).unwrap().starts_with("-96"));
+    }
+}
@@ -0,0 +1,230 @@
+use image::imageops;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::screen::{self, CaptureSource, DisplaySelector, EncodeOptions, Region, Screenshot};
+use crate::settings;
+
+const DEFAULT_INTERVAL_MS: u64 = 2000;
+const MIN_INTERVAL_MS: u64 = 250;
+/// Hash grid side; the hash has `HASH_SIZE²` bits.
+const HASH_SIZE: u32 = 16;
+/// Differing hash bits (of 256) above which the screen counts as changed.
+const DEFAULT_THRESHOLD: u32 = 20;
+
+/// What to sample and how often. Unset fields fall back to the
+/// `auto_capture_*` settings, then to `capture_screenshot`'s defaults.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct AutoCaptureOptions {
+    pub display: Option<DisplaySelector>,
+    pub window: Option<i64>,
+    pub region: Option<Region>,
+    pub interval_ms: Option<u64>,
+    pub threshold: Option<u32>,
+    pub encoding: Option<EncodeOptions>,
+    pub ocr: Option<bool>,
+}
+
+/// Emitted as `screen-changed` for every capture that is kept.
+#[derive(Serialize, Clone, Debug)]
+pub struct ScreenChange {
+    /// Hash bits that differ from the previous kept capture; `None` for the first.
+    pub distance: Option<u32>,
+    pub hash: String,
+    pub screenshot: Screenshot,
+}
+
+struct AutoCaptureHandle {
+    /// Dropped to stop the sampler.
+    _stop: mpsc::Sender<()>,
+}
+
+/// Managed state: the running auto-capture sampler, if any.
+pub struct AutoCapture(Mutex<Option<AutoCaptureHandle>>);
+
+impl AutoCapture {
+    pub fn new() -> Self {
+        AutoCapture(Mutex::new(None))
+    }
+}
+
+/// Start sampling the screen every `interval_ms`. A sample is kept, stored in
+/// the session and emitted only when its perceptual hash differs from the
+/// last kept one by more than `threshold` bits and the screen has settled,
+/// i.e. it matches the previous sample, so slide transitions aren't captured.
+pub fn start(app: &AppHandle, options: AutoCaptureOptions) -> Result<(), String> {
+    let state = app.state::<AutoCapture>();
+    let mut guard = state.0.lock().unwrap();
+    if guard.is_some() {
+        return Err("Auto-capture is already running".to_string());
+    }
+
+    let interval_ms = options
+        .interval_ms
+        .or_else(|| settings::get(app, "auto_capture_interval_ms"))
+        .unwrap_or(DEFAULT_INTERVAL_MS)
+        .max(MIN_INTERVAL_MS);
+    let threshold = options
+        .threshold
+        .or_else(|| settings::get(app, "auto_capture_threshold"))
+        .unwrap_or(DEFAULT_THRESHOLD);
+
+    let (tx, rx) = mpsc::channel::<()>();
+    let app_handle = app.clone();
+    std::thread::Builder::new()
+        .name("auto-capture".into())
+        .spawn(move || {
+            let source = CaptureSource { display: options.display, window: options.window, region: options.region };
+            let mut sampler = Sampler { threshold, kept: None, previous: None };
+            let mut last_error = None;
+
+            // Exits once the sender is dropped by `stop`.
+            while let Err(RecvTimeoutError::Timeout) = rx.recv_timeout(Duration::from_millis(interval_ms)) {
+                let result = screen::grab(&app_handle, &source)
+                    .and_then(|image| sampler.sample(&app_handle, image, &source, &options));
+                match result {
+                    Ok(()) => last_error = None,
+                    // A closed window or unplugged display fails every tick; say so once.
+                    Err(e) if last_error.as_ref() != Some(&e) => {
+                        eprintln!("[AutoCapture] ✗ {}", e);
+                        last_error = Some(e);
+                    }
+                    Err(_) => {}
+                }
+            }
+            println!("[AutoCapture] Stopped");
+        })
+        .map_err(|e| format!("Failed to spawn auto-capture thread: {}", e))?;
+
+    println!("[AutoCapture] ✓ Every {} ms, threshold {} bits", interval_ms, threshold);
+    *guard = Some(AutoCaptureHandle { _stop: tx });
+    Ok(())
+}
+
+pub fn stop(app: &AppHandle) {
+    if app.state::<AutoCapture>().0.lock().unwrap().take().is_some() {
+        println!("[AutoCapture] Stopping");
+    }
+}
+
+struct Sampler {
+    threshold: u32,
+    /// Hash of the last kept capture.
+    kept: Option<Hash>,
+    /// Hash of the last sample, kept or not.
+    previous: Option<Hash>,
+}
+
+/// What to do with a sample.
+#[derive(PartialEq, Debug)]
+enum Decision {
+    Skip,
+    /// Keep it; `distance` from the last kept capture, `None` for the first.
+    Keep { distance: Option<u32> },
+}
+
+impl Sampler {
+    fn sample(
+        &mut self,
+        app: &AppHandle,
+        image: RgbaImage,
+        source: &CaptureSource,
+        options: &AutoCaptureOptions,
+    ) -> Result<(), String> {
+        let hash = dhash(&image);
+        let Decision::Keep { distance } = self.decide(hash) else { return Ok(()) };
+        let screenshot = screen::process(app, image, Some(source), options.encoding.unwrap_or_default(), options.ocr)?;
+        // Only now: a capture that failed to process is retried next tick.
+        self.kept = Some(hash);
+        let hash = hash.iter().map(|word| format!("{:016x}", word)).collect();
+        let _ = app.emit("screen-changed", ScreenChange { distance, hash, screenshot });
+        Ok(())
+    }
+
+    /// Keep a sample when it differs from the last kept one by more than the
+    /// threshold and matches the previous sample, i.e. the screen settled.
+    fn decide(&mut self, hash: Hash) -> Decision {
+        let settled = self.previous.is_none_or(|previous| distance(&previous, &hash) <= self.threshold);
+        self.previous = Some(hash);
+
+        let distance = self.kept.map(|kept| distance(&kept, &hash));
+        if distance.is_some_and(|d| d <= self.threshold) || !settled {
+            return Decision::Skip;
+        }
+        Decision::Keep { distance }
+    }
+}
+
+type Hash = [u64; (HASH_SIZE * HASH_SIZE / 64) as usize];
+
+/// Difference hash: each bit says whether a cell of a grayscale thumbnail is
+/// brighter than its right neighbour. Robust to scaling and small shifts,
+/// sensitive to changed layout and text.
+fn dhash(image: &RgbaImage) -> Hash {
+    let thumb = imageops::thumbnail(&imageops::grayscale(image), HASH_SIZE + 1, HASH_SIZE);
+    let mut hash = Hash::default();
+    for y in 0..HASH_SIZE {
+        for x in 0..HASH_SIZE {
+            if thumb.get_pixel(x, y)[0] > thumb.get_pixel(x + 1, y)[0] {
+                let bit = (y * HASH_SIZE + x) as usize;
+                hash[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+    }
+    hash
+}
+
+fn distance(a: &Hash, b: &Hash) -> u32 {
+    a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A horizontal gradient, brightening to the right or to the left.
+    fn gradient(rightwards: bool) -> RgbaImage {
+        RgbaImage::from_fn(170, 160, |x, _| {
+            let v = if rightwards { x } else { 169 - x } as u8;
+            image::Rgba([v, v, v, 255])
+        })
+    }
+
+    fn sampler() -> Sampler {
+        Sampler { threshold: DEFAULT_THRESHOLD, kept: None, previous: None }
+    }
+
+    #[test]
+    fn identical_frames_hash_the_same() {
+        assert_eq!(distance(&dhash(&gradient(true)), &dhash(&gradient(true))), 0);
+        assert_eq!(distance(&dhash(&gradient(true)), &dhash(&gradient(false))), HASH_SIZE * HASH_SIZE);
+    }
+
+    #[test]
+    fn first_sample_is_kept_and_repeats_skipped() {
+        let mut sampler = sampler();
+        let hash = dhash(&gradient(true));
+        assert_eq!(sampler.decide(hash), Decision::Keep { distance: None });
+        sampler.kept = Some(hash);
+        assert_eq!(sampler.decide(hash), Decision::Skip);
+        assert_eq!(sampler.decide(hash), Decision::Skip);
+    }
+
+    #[test]
+    fn changed_screen_is_kept_once_it_settles() {
+        let mut sampler = sampler();
+        let (before, after) = (dhash(&gradient(true)), dhash(&gradient(false)));
+        sampler.decide(before);
+        sampler.kept = Some(before);
+
+        // Still changing: it differs from the previous sample.
+        assert_eq!(sampler.decide(after), Decision::Skip);
+        assert_eq!(sampler.decide(after), Decision::Keep { distance: Some(HASH_SIZE * HASH_SIZE) });
+        // Not marked kept (processing failed): offered again next tick.
+        assert_eq!(sampler.decide(after), Decision::Keep { distance: Some(HASH_SIZE * HASH_SIZE) });
+    }
+}
@@ -0,0 +1,157 @@
+use arboard::Clipboard;
+use image::RgbaImage;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::settings;
+
+const DEFAULT_POLL_MS: u64 = 1000;
+const MIN_POLL_MS: u64 = 250;
+/// Pixels hashed to fingerprint a clipboard image; hashing all of a 4K image
+/// every poll costs tens of megabytes of reads.
+const IMAGE_SAMPLES: usize = 4096;
+
+/// New clipboard content, emitted as `clipboard-changed` by the watcher.
+/// Images are only described; `read_clipboard_image` fetches one.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClipboardChange {
+    Text { text: String },
+    Image { width: u32, height: u32 },
+}
+
+struct WatcherHandle {
+    /// Dropped to stop the watcher.
+    _stop: mpsc::Sender<()>,
+}
+
+/// Managed state: the running clipboard watcher, if any.
+pub struct ClipboardWatcher(Mutex<Option<WatcherHandle>>);
+
+impl ClipboardWatcher {
+    pub fn new() -> Self {
+        ClipboardWatcher(Mutex::new(None))
+    }
+}
+
+fn open() -> Result<Clipboard, String> {
+    Clipboard::new().map_err(|e| format!("Failed to open clipboard: {}", e))
+}
+
+/// The clipboard as text, or `None` when it holds no text.
+pub fn read_text() -> Result<Option<String>, String> {
+    match open()?.get_text() {
+        Ok(text) if !text.trim().is_empty() => Ok(Some(text)),
+        Ok(_) | Err(arboard::Error::ContentNotAvailable) => Ok(None),
+        Err(e) => Err(format!("Failed to read clipboard text: {}", e)),
+    }
+}
+
+/// The clipboard as an image, or `None` when it holds no image.
+pub fn read_image() -> Result<Option<RgbaImage>, String> {
+    read_image_from(&mut open()?)
+}
+
+fn read_image_from(clipboard: &mut Clipboard) -> Result<Option<RgbaImage>, String> {
+    match clipboard.get_image() {
+        Ok(data) => RgbaImage::from_raw(data.width as u32, data.height as u32, data.bytes.into_owned())
+            .map(Some)
+            .ok_or_else(|| "Invalid clipboard image".to_string()),
+        Err(arboard::Error::ContentNotAvailable) => Ok(None),
+        Err(e) => Err(format!("Failed to read clipboard image: {}", e)),
+    }
+}
+
+/// Poll the clipboard every `clipboard_poll_ms` (default 1000) and emit a
+/// `clipboard-changed` event when something new is copied. Content already on
+/// the clipboard when the watcher starts is not reported.
+pub fn start(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<ClipboardWatcher>();
+    let mut guard = state.0.lock().unwrap();
+    if guard.is_some() {
+        return Err("Clipboard watcher is already running".to_string());
+    }
+    let poll_ms = settings::get::<u64>(app, "clipboard_poll_ms").unwrap_or(DEFAULT_POLL_MS).max(MIN_POLL_MS);
+    let mut clipboard = open()?;
+
+    let (tx, rx) = mpsc::channel::<()>();
+    let app_handle = app.clone();
+    std::thread::Builder::new()
+        .name("clipboard-watcher".into())
+        .spawn(move || {
+            let mut last_sequence = sequence_number();
+            let mut last = fingerprint(&mut clipboard).0;
+
+            // Exits once the sender is dropped by `stop`.
+            while let Err(RecvTimeoutError::Timeout) = rx.recv_timeout(Duration::from_millis(poll_ms)) {
+                let sequence = sequence_number();
+                if sequence.is_some() && sequence == last_sequence {
+                    continue;
+                }
+                last_sequence = sequence;
+                let (current, change) = fingerprint(&mut clipboard);
+                if current == last {
+                    continue;
+                }
+                last = current;
+                if let Some(change) = change {
+                    let _ = app_handle.emit("clipboard-changed", change);
+                }
+            }
+            println!("[Clipboard] Watcher stopped");
+        })
+        .map_err(|e| format!("Failed to spawn clipboard watcher: {}", e))?;
+
+    println!("[Clipboard] ✓ Watching every {} ms", poll_ms);
+    *guard = Some(WatcherHandle { _stop: tx });
+    Ok(())
+}
+
+pub fn stop(app: &AppHandle) {
+    if app.state::<ClipboardWatcher>().0.lock().unwrap().take().is_some() {
+        println!("[Clipboard] Stopping watcher");
+    }
+}
+
+/// The OS clipboard change counter, where there is one, so polls can skip
+/// reading an unchanged clipboard.
+fn sequence_number() -> Option<u32> {
+    #[cfg(target_os = "windows")]
+    {
+        // 0 means the counter isn't available to this session.
+        Some(unsafe { winapi::um::winuser::GetClipboardSequenceNumber() }).filter(|n| *n != 0)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        None
+    }
+}
+
+/// A hash of the current content, and the change to report if it is new.
+/// Text wins when the clipboard offers both. Images are hashed by size and
+/// an even sample of their pixels.
+fn fingerprint(clipboard: &mut Clipboard) -> (Option<u64>, Option<ClipboardChange>) {
+    let mut hasher = DefaultHasher::new();
+    if let Ok(text) = clipboard.get_text() {
+        if !text.trim().is_empty() {
+            text.hash(&mut hasher);
+            return (Some(hasher.finish()), Some(ClipboardChange::Text { text }));
+        }
+    }
+    if let Ok(Some(image)) = read_image_from(clipboard) {
+        image.dimensions().hash(&mut hasher);
+        let pixels = image.as_raw().chunks_exact(4);
+        let step = (pixels.len() / IMAGE_SAMPLES).max(1);
+        for pixel in pixels.step_by(step) {
+            pixel.hash(&mut hasher);
+        }
+        let (width, height) = image.dimensions();
+        return (Some(hasher.finish()), Some(ClipboardChange::Image { width, height }));
+    }
+    (None, None)
+}
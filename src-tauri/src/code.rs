@@ -0,0 +1,363 @@
+use serde::Serialize;
+
+use crate::ocr::TextBlock;
+
+/// Lines further apart than this many line heights start a new region.
+const MAX_LINE_GAP: f32 = 1.5;
+/// Share of lines that must look like code for a region to count as code.
+const MIN_CODE_SHARE: f32 = 0.5;
+/// Widest indentation step considered, in characters.
+const MAX_INDENT_UNIT: usize = 8;
+
+/// A run of OCR lines recognized as source code, with its indentation rebuilt.
+#[derive(Serialize, Clone, Debug)]
+pub struct CodeBlock {
+    pub code: String,
+    /// Markdown fence name (`rust`, `python`, …), if a guess could be made.
+    pub language: Option<String>,
+    pub lines: usize,
+    /// Whether a line-number gutter was removed.
+    pub gutter: bool,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub confidence: f32,
+}
+
+/// OCR text with code regions reconstructed.
+pub struct Layout {
+    /// Reading-order text; code regions become fenced Markdown blocks.
+    pub text: String,
+    pub code: Vec<CodeBlock>,
+}
+
+struct Line<'a> {
+    block: &'a TextBlock,
+    /// Text without a line number.
+    text: &'a str,
+    /// Where the text starts, past any line number.
+    start: f32,
+    numbered: bool,
+}
+
+/// Split OCR lines into prose and code regions and rebuild each code region.
+pub fn reconstruct(blocks: &[TextBlock]) -> Layout {
+    let (gutters, blocks): (Vec<&TextBlock>, Vec<&TextBlock>) = blocks.iter().partition(|b| is_gutter(b, blocks));
+    let mut sections = Vec::new();
+    let mut code = Vec::new();
+
+    for region in regions(&blocks) {
+        let rebuilt = char_width(&region)
+            .filter(|w| *w > 0.0)
+            .and_then(|width| rebuild(&strip_line_numbers(&region, &gutters), width));
+        match rebuilt {
+            Some(block) => {
+                let fence = block.language.as_deref().unwrap_or("");
+                sections.push(format!("```{}\n{}\n```", fence, block.code));
+                code.push(block);
+            }
+            None => sections.push(region.iter().map(|b| b.text.as_str()).collect::<Vec<_>>().join("\n")),
+        }
+    }
+    Layout { text: sections.join("\n"), code }
+}
+
+/// A bare line number that shares its row with text to its right.
+fn is_gutter(block: &TextBlock, all: &[TextBlock]) -> bool {
+    let text = block.text.trim();
+    if text.is_empty() || text.len() > 5 || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    all.iter().any(|other| other.x >= block.x + block.width && same_row(block, other))
+}
+
+fn same_row(a: &TextBlock, b: &TextBlock) -> bool {
+    let overlap = (a.y + a.height).min(b.y + b.height) as i64 - a.y.max(b.y) as i64;
+    overlap * 2 >= a.height.min(b.height) as i64
+}
+
+/// Group consecutive lines that sit close together and overlap horizontally.
+fn regions<'a>(blocks: &[&'a TextBlock]) -> Vec<Vec<&'a TextBlock>> {
+    let line_height = median(blocks.iter().map(|b| b.height as f32)).unwrap_or(0.0);
+    let mut regions: Vec<Vec<&TextBlock>> = Vec::new();
+    let mut extent = (0, 0, 0);
+
+    for &block in blocks {
+        let (left, right, bottom) = extent;
+        let joins = regions.last().is_some()
+            && (block.y as f32) - (bottom as f32) <= line_height * MAX_LINE_GAP
+            && block.y + block.height > bottom
+            && block.x < right
+            && block.x + block.width > left;
+        if joins {
+            regions.last_mut().unwrap().push(block);
+            extent = (left.min(block.x), right.max(block.x + block.width), bottom.max(block.y + block.height));
+        } else {
+            regions.push(vec![block]);
+            extent = (block.x, block.x + block.width, block.y + block.height);
+        }
+    }
+    regions
+}
+
+/// Monospace character width, from word boxes where OCR provided them.
+fn char_width(region: &[&TextBlock]) -> Option<f32> {
+    median(
+        region
+            .iter()
+            .flat_map(|b| &b.words)
+            .map(|w| (w.width as f32, w.text.chars().count()))
+            .filter(|(_, chars)| *chars >= 2)
+            .map(|(width, chars)| width / chars as f32),
+    )
+    .or_else(|| median(region.iter().map(|b| b.width as f32 / b.text.chars().count().max(1) as f32)))
+}
+
+/// Remove `12 ` style prefixes when most lines carry increasing numbers.
+/// Lines next to a separately recognized `gutters` number count as numbered.
+fn strip_line_numbers<'a>(region: &[&'a TextBlock], gutters: &[&TextBlock]) -> Vec<Line<'a>> {
+    let numbers: Vec<Option<(u64, &str)>> = region.iter().map(|b| line_number(&b.text)).collect();
+    let numbered = numbers.iter().filter(|n| n.is_some()).count();
+    let increasing = numbers.iter().flatten().map(|(n, _)| *n).collect::<Vec<_>>().windows(2).all(|w| w[0] < w[1]);
+    let strip = region.len() >= 2 && numbered * 10 >= region.len() * 6 && increasing;
+
+    region
+        .iter()
+        .zip(numbers)
+        .map(|(block, number)| match number {
+            // The code starts at the second word.
+            Some((_, rest)) if strip => {
+                let start = block.words.get(1).map_or(block.x, |w| w.x) as f32;
+                Line { block, text: rest, start, numbered: true }
+            }
+            _ => {
+                let start = block.words.first().map_or(block.x, |w| w.x) as f32;
+                let numbered = gutters.iter().any(|g| g.x < block.x && same_row(g, block));
+                Line { block, text: block.text.trim(), start, numbered }
+            }
+        })
+        .collect()
+}
+
+fn line_number(text: &str) -> Option<(u64, &str)> {
+    let text = text.trim_start();
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 || digits > 5 {
+        return None;
+    }
+    let rest = &text[digits..];
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some((text[..digits].parse().ok()?, rest.trim()))
+}
+
+/// Rebuild a region as code, or `None` if it reads like prose.
+fn rebuild(lines: &[Line], char_width: f32) -> Option<CodeBlock> {
+    let gutter = lines.iter().any(|l| l.numbered);
+    let code_lines = lines.iter().filter(|l| looks_like_code(l.text)).count();
+    if lines.len() < 2 || (!gutter && (code_lines as f32) < lines.len() as f32 * MIN_CODE_SHARE) {
+        return None;
+    }
+
+    let starts: Vec<f32> = lines.iter().map(|l| l.start).collect();
+    let left = starts.iter().copied().fold(f32::INFINITY, f32::min);
+    let columns: Vec<usize> = starts.iter().map(|s| ((s - left) / char_width).round() as usize).collect();
+
+    // Snap to the smallest indentation step so OCR jitter doesn't leave odd columns.
+    let unit = columns.iter().copied().filter(|c| *c > 0).min().unwrap_or(1).clamp(1, MAX_INDENT_UNIT);
+    let code = lines
+        .iter()
+        .zip(&columns)
+        .map(|(line, column)| {
+            let indent = ((*column as f32 / unit as f32).round() as usize) * unit;
+            format!("{}{}", " ".repeat(indent), line.text)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let x = lines.iter().map(|l| l.block.x).min().unwrap_or(0);
+    let y = lines.iter().map(|l| l.block.y).min().unwrap_or(0);
+    let right = lines.iter().map(|l| l.block.x + l.block.width).max().unwrap_or(x);
+    let bottom = lines.iter().map(|l| l.block.y + l.block.height).max().unwrap_or(y);
+    Some(CodeBlock {
+        language: guess_language(&code).map(str::to_string),
+        code,
+        lines: lines.len(),
+        gutter,
+        x,
+        y,
+        width: right - x,
+        height: bottom - y,
+        confidence: lines.iter().map(|l| l.block.confidence).sum::<f32>() / lines.len() as f32,
+    })
+}
+
+const CODE_ENDINGS: &[&str] = &["{", "}", ";", "(", ")", "[", "]", ",", ":", "=>", "->", "\\"];
+const CODE_MARKERS: &[&str] = &[
+    "==", "!=", "=>", "->", "::", "&&", "||", "+=", " = ", "();", "//", "/*", "</", "/>", "#include", "${",
+];
+const CODE_KEYWORDS: &[&str] = &[
+    "fn ", "def ", "class ", "return", "import ", "from ", "let ", "const ", "var ", "if ", "if(", "for ", "for(",
+    "while ", "else", "elif ", "try", "catch", "except", "pub ", "use ", "func ", "package ", "public ", "private ",
+    "static ", "void ", "int ", "struct ", "impl ", "#", "@", "$ ", "SELECT ", "FROM ", "WHERE ", "ORDER BY ",
+];
+
+fn looks_like_code(line: &str) -> bool {
+    CODE_ENDINGS.iter().any(|e| line.ends_with(e))
+        || CODE_MARKERS.iter().any(|m| line.contains(m))
+        || CODE_KEYWORDS.iter().any(|k| line.starts_with(k))
+        || line.split('(').next().is_some_and(|head| head.len() < line.len() && head.ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+}
+
+/// Tokens and weights per language. A language later in the list must score
+/// strictly higher to win, so supersets (TypeScript, C++) follow their base.
+const LANGUAGES: &[(&str, &[(&str, u32)])] = &[
+    ("rust", &[("fn ", 2), ("let mut ", 3), ("impl ", 3), ("pub fn", 3), ("::", 1), ("-> ", 1), ("&self", 3), ("println!", 3), ("Vec<", 2), ("match ", 1), ("Ok(", 1), ("Some(", 1)]),
+    ("python", &[("def ", 3), ("self.", 1), ("import ", 1), ("elif ", 3), ("None", 1), ("print(", 2), ("__init__", 3), ("True", 1), ("False", 1), (" in range(", 3), ("):\n", 2)]),
+    ("javascript", &[("const ", 1), ("=> ", 1), ("function ", 2), ("console.log", 3), ("===", 2), ("let ", 1), ("require(", 2), ("document.", 2), ("async ", 1), ("await ", 1)]),
+    ("typescript", &[("const ", 1), ("=> ", 1), ("function ", 2), ("console.log", 3), ("===", 2), ("let ", 1), ("async ", 1), ("await ", 1), ("interface ", 3), (": string", 3), (": number", 3), (": boolean", 3), ("export ", 1), ("type ", 1)]),
+    ("go", &[("func ", 3), (":= ", 2), ("package ", 3), ("fmt.", 3), ("err != nil", 3), ("chan ", 2), ("go func", 3)]),
+    ("java", &[("public class", 3), ("System.out", 3), ("void ", 1), ("private ", 1), ("public static", 2), ("String[]", 3), ("@Override", 3), ("new ", 1)]),
+    ("csharp", &[("using System", 3), ("namespace ", 2), ("Console.Write", 3), ("public void", 1), ("var ", 1), ("string ", 1), ("=> ", 1), ("{ get;", 3)]),
+    ("kotlin", &[("fun ", 3), ("val ", 2), ("var ", 1), ("println(", 1), ("?.", 1), ("data class", 3)]),
+    ("c", &[("#include", 3), ("printf(", 3), ("int main", 2), ("malloc(", 3), ("NULL", 1), ("struct ", 1), ("->", 1)]),
+    ("cpp", &[("#include", 3), ("printf(", 3), ("int main", 2), ("NULL", 1), ("->", 1), ("std::", 3), ("cout", 3), ("nullptr", 3), ("template<", 3), ("vector<", 2)]),
+    ("sql", &[("SELECT ", 3), ("FROM ", 2), ("WHERE ", 2), ("INSERT INTO", 3), ("JOIN ", 2), ("GROUP BY", 3), ("CREATE TABLE", 3)]),
+    ("bash", &[("$ ", 2), ("sudo ", 3), ("echo ", 2), ("export ", 1), ("npm ", 2), ("cd ", 2), ("git ", 2), ("#!/bin", 3), ("fi\n", 3)]),
+];
+
+/// Guess a language from keyword counts; `None` if nothing stands out.
+fn guess_language(code: &str) -> Option<&'static str> {
+    let mut best = None;
+    let mut best_score = 1; // at least two weighted hits
+    for (language, tokens) in LANGUAGES {
+        let score: u32 = tokens.iter().map(|(token, weight)| code.matches(token).count() as u32 * weight).sum();
+        if score > best_score {
+            best = Some(*language);
+            best_score = score;
+        }
+    }
+    best
+}
+
+fn median(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let mut values: Vec<f32> = values.collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    Some(values[values.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::Word;
+
+    const CHAR: u32 = 10;
+    const HEIGHT: u32 = 20;
+
+    /// An OCR line as a monospace renderer would place it: leading spaces
+    /// become the block's offset, each word gets its own box.
+    fn line(text: &str, x: u32, row: u32) -> TextBlock {
+        let mut words = Vec::new();
+        let mut start = None;
+        for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(i),
+                (true, Some(s)) => {
+                    words.push(Word { text: text[s..i].to_string(), x: x + s as u32 * CHAR, width: (i - s) as u32 * CHAR });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        let left = words.first().map_or(x, |w| w.x);
+        let right = words.last().map_or(x, |w| w.x + w.width);
+        TextBlock {
+            text: text.trim().to_string(),
+            x: left,
+            y: row * (HEIGHT + 4),
+            width: right - left,
+            height: HEIGHT,
+            confidence: 0.9,
+            words,
+        }
+    }
+
+    fn lines(texts: &[&str]) -> Vec<TextBlock> {
+        texts.iter().enumerate().map(|(row, text)| line(text, 100, row as u32)).collect()
+    }
+
+    #[test]
+    fn prose_is_left_alone() {
+        let blocks = lines(&["The interviewer asked about caching.", "We talked about eviction policies"]);
+        let layout = reconstruct(&blocks);
+        assert!(layout.code.is_empty());
+        assert_eq!(layout.text, "The interviewer asked about caching.\nWe talked about eviction policies");
+    }
+
+    #[test]
+    fn indentation_is_rebuilt_and_snapped() {
+        let mut blocks = lines(&["fn main() {", "    let mut v = Vec::new();", "    v.push(1);", "}"]);
+        // OCR jitter: a third of a character off.
+        blocks[2].x += 3;
+        for word in &mut blocks[2].words {
+            word.x += 3;
+        }
+        let layout = reconstruct(&blocks);
+        assert_eq!(layout.code.len(), 1);
+        let block = &layout.code[0];
+        assert_eq!(block.code, "fn main() {\n    let mut v = Vec::new();\n    v.push(1);\n}");
+        assert_eq!(block.language.as_deref(), Some("rust"));
+        assert!(!block.gutter);
+        assert!(layout.text.starts_with("```rust\n"));
+    }
+
+    #[test]
+    fn line_number_prefixes_are_stripped() {
+        let blocks = lines(&["1 def area(r):", "2     return 3.14 * r * r", "3 print(area(2))"]);
+        let block = &reconstruct(&blocks).code[0];
+        assert_eq!(block.code, "def area(r):\n    return 3.14 * r * r\nprint(area(2))");
+        assert_eq!(block.language.as_deref(), Some("python"));
+        assert!(block.gutter);
+    }
+
+    #[test]
+    fn separate_gutter_blocks_are_dropped() {
+        let mut blocks = Vec::new();
+        for (row, text) in ["SELECT name", "FROM users", "WHERE id = 1"].iter().enumerate() {
+            blocks.push(line(&(row + 1).to_string(), 40, row as u32));
+            blocks.push(line(text, 100, row as u32));
+        }
+        let layout = reconstruct(&blocks);
+        assert_eq!(layout.code.len(), 1);
+        assert_eq!(layout.code[0].code, "SELECT name\nFROM users\nWHERE id = 1");
+        assert_eq!(layout.code[0].language.as_deref(), Some("sql"));
+        assert!(layout.code[0].gutter);
+    }
+
+    #[test]
+    fn code_lines_are_recognized() {
+        assert!(looks_like_code("if (x > 0) {"));
+        assert!(looks_like_code("return a + b;"));
+        assert!(looks_like_code("result = compute(x)"));
+        assert!(!looks_like_code("This sentence is plain prose"));
+    }
+
+    #[test]
+    fn line_numbers_need_a_separator() {
+        assert_eq!(line_number("12   let x = 1;"), Some((12, "let x = 1;")));
+        assert_eq!(line_number("2024"), None);
+        assert_eq!(line_number("3d model"), None);
+    }
+
+    #[test]
+    fn languages_are_guessed_from_keywords() {
+        assert_eq!(guess_language("package main\nfunc main() {\n    fmt.Println(x)\n}"), Some("go"));
+        assert_eq!(guess_language("interface User {\n  name: string;\n}"), Some("typescript"));
+        assert_eq!(guess_language("x = 1\ny = 2"), None);
+    }
+}
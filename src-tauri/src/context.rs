@@ -0,0 +1,325 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::llm::{LlmClient, LlmRequest};
+use crate::prompts;
+use crate::sessions::{SessionStore, StoredSegment};
+use crate::settings;
+
+const DEFAULT_TOKEN_BUDGET: usize = 3000;
+/// Share of the budget for verbatim transcript; summaries get the rest.
+const VERBATIM_SHARE: f64 = 0.6;
+/// Overflow is folded into a summary once it reaches this share of the
+/// budget, so the summarizer isn't called for every new segment. Until then
+/// it stays verbatim and the budget is slightly exceeded.
+const FOLD_SHARE: f64 = 0.15;
+const MAX_PENDING_SEGMENTS: usize = 2000;
+const SUMMARY_MAX_OUTPUT_TOKENS: u32 = 400;
+
+struct Summary {
+    text: String,
+    tokens: usize,
+}
+
+#[derive(Default)]
+struct State {
+    session_id: Option<i64>,
+    summaries: Vec<Summary>,
+    /// Highest segment ID already folded into `summaries`.
+    summarized_upto: i64,
+    folding: bool,
+}
+
+/// Managed state: rolling summaries for the active session. Recent transcript
+/// is kept verbatim; older segments are folded into summaries, by the model
+/// (`summary` prompt) or locally.
+pub struct ContextManager(Mutex<State>);
+
+impl ContextManager {
+    pub fn new() -> Self {
+        ContextManager(Mutex::new(State::default()))
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Context {
+    /// Rolling summary of everything older than `transcript`.
+    pub summary: String,
+    /// Recent segments, one `Speaker: text` line each.
+    pub transcript: String,
+    pub summary_tokens: usize,
+    pub transcript_tokens: usize,
+    pub budget: usize,
+    /// A summary is being generated in the background.
+    pub folding: bool,
+}
+
+/// Build the context for the next model request. Starts folding overflowed
+/// segments into a summary in the background when needed.
+pub fn build(app: &AppHandle) -> Context {
+    let budget = token_budget(app);
+    let (Some(sessions), Some(manager)) = (app.try_state::<SessionStore>(), app.try_state::<ContextManager>()) else {
+        return Context { budget, ..Default::default() };
+    };
+    let mut state = manager.0.lock().unwrap();
+    let active = sessions.active_id();
+    if state.session_id != active {
+        *state = State { session_id: active, ..Default::default() };
+    }
+    let Some(session_id) = active else { return Context { budget, ..Default::default() } };
+
+    let pending = sessions.segments_since(state.summarized_upto, MAX_PENDING_SEGMENTS).unwrap_or_else(|e| {
+        eprintln!("[Context] ✗ {}", e);
+        Vec::new()
+    });
+    let lines: Vec<String> = pending.iter().map(transcript_line).collect();
+
+    let keep_from = split(&lines, budget, state.folding);
+    let mut summaries: Vec<String> = state.summaries.iter().map(|s| s.text.clone()).collect();
+    if keep_from > 0 {
+        if !state.folding {
+            state.folding = true;
+            let overflow = pending[..keep_from].to_vec();
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move { fold(app, session_id, overflow).await });
+        }
+        // Until the fold lands, a quick extractive summary stands in for the
+        // cut-off lines, so they don't vanish from the prompt meanwhile.
+        let summary_budget = budget - (budget as f64 * VERBATIM_SHARE) as usize;
+        let room = summary_budget.saturating_sub(state.summaries.iter().map(|s| s.tokens).sum());
+        let provisional = keep_last_lines(&summarize_locally(&pending[..keep_from]), room);
+        if room > 0 && !provisional.is_empty() {
+            summaries.push(provisional);
+        }
+    }
+
+    let transcript = lines[keep_from..].join("\n");
+    let summary = summaries.join("\n");
+    Context {
+        summary_tokens: estimate_tokens(&summary),
+        transcript_tokens: estimate_tokens(&transcript),
+        summary,
+        transcript,
+        budget,
+        folding: state.folding,
+    }
+}
+
+/// Where the verbatim part of `lines` starts: newest first until the verbatim
+/// share is used up, always keeping the last line. While the overflow is too
+/// small to fold, and no fold is running, everything stays verbatim.
+fn split(lines: &[String], budget: usize, folding: bool) -> usize {
+    let verbatim_budget = (budget as f64 * VERBATIM_SHARE) as usize;
+    let mut used = 0;
+    let mut keep_from = lines.len();
+    for (i, line) in lines.iter().enumerate().rev() {
+        let tokens = estimate_tokens(line);
+        if used + tokens > verbatim_budget && keep_from < lines.len() {
+            break;
+        }
+        used += tokens;
+        keep_from = i;
+    }
+    let overflow_tokens: usize = lines[..keep_from].iter().map(|l| estimate_tokens(l)).sum();
+    if overflow_tokens < (budget as f64 * FOLD_SHARE) as usize && !folding {
+        0
+    } else {
+        keep_from
+    }
+}
+
+/// Summarize `segments` and append the result to the rolling summary. When
+/// the summaries outgrow their share of the budget, they are merged into one.
+async fn fold(app: AppHandle, session_id: i64, segments: Vec<StoredSegment>) {
+    let Some(upto) = segments.last().map(|s| s.id) else { return };
+    let budget = token_budget(&app);
+    let summary_budget = budget - (budget as f64 * VERBATIM_SHARE) as usize;
+    let use_model = settings::get::<String>(&app, "context_summarizer").as_deref() != Some("local");
+    let manager = app.state::<ContextManager>();
+    let previous = {
+        let state = manager.0.lock().unwrap();
+        state.summaries.iter().map(|s| s.text.clone()).collect::<Vec<_>>().join("\n")
+    };
+
+    println!("[Context] Folding {} segments into the summary", segments.len());
+    let transcript = segments.iter().map(transcript_line).collect::<Vec<_>>().join("\n");
+    let mut text = if use_model {
+        match summarize_with_model(&app, &transcript, &previous).await {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("[Context] ✗ Model summary failed, summarizing locally: {}", e);
+                summarize_locally(&segments)
+            }
+        }
+    } else {
+        summarize_locally(&segments)
+    };
+
+    let mut merged = false;
+    if estimate_tokens(&previous) + estimate_tokens(&text) > summary_budget {
+        let combined = format!("{}\n{}", previous, text);
+        text = if use_model {
+            summarize_with_model(&app, &combined, "").await.unwrap_or(combined)
+        } else {
+            combined
+        };
+        text = keep_last_lines(&text, summary_budget);
+        merged = true;
+    }
+
+    let mut state = manager.0.lock().unwrap();
+    // A new session reset the state; its own fold may be running.
+    if state.session_id != Some(session_id) {
+        return;
+    }
+    state.folding = false;
+    if merged {
+        state.summaries.clear();
+    }
+    state.summaries.push(Summary { tokens: estimate_tokens(&text), text });
+    state.summarized_upto = upto;
+    println!(
+        "[Context] ✓ Summary now {} tokens in {} part(s)",
+        state.summaries.iter().map(|s| s.tokens).sum::<usize>(),
+        state.summaries.len()
+    );
+}
+
+async fn summarize_with_model(app: &AppHandle, transcript: &str, previous: &str) -> Result<String, String> {
+    let mut request = LlmRequest {
+        template: Some("summary".to_string()),
+        variables: HashMap::from([
+            ("transcript".to_string(), transcript.to_string()),
+            ("summary".to_string(), previous.to_string()),
+        ]),
+        temperature: Some(0.2),
+        max_output_tokens: Some(SUMMARY_MAX_OUTPUT_TOKENS),
+        ..Default::default()
+    };
+    prompts::apply(app, &mut request)?;
+    let response = app.state::<LlmClient>().generate(app, &request).await.map_err(|e| e.to_string())?;
+    let text = response.text.trim();
+    if text.is_empty() {
+        return Err("empty summary".to_string());
+    }
+    Ok(text.to_string())
+}
+
+/// Extractive fallback: per segment, its first question (or first sentence).
+fn summarize_locally(segments: &[StoredSegment]) -> String {
+    segments
+        .iter()
+        .filter_map(|seg| {
+            let text = seg.text.trim();
+            let mut sentences = text.split_inclusive(['.', '!', '?']).map(str::trim).filter(|s| !s.is_empty());
+            let first = sentences.clone().next()?;
+            let picked = sentences.find(|s| s.ends_with('?')).unwrap_or(first);
+            let picked: String = picked.chars().take(200).collect();
+            Some(format!("- {}: {}", capitalize(&seg.speaker), picked))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The newest lines of `text` that fit in `budget` tokens.
+fn keep_last_lines(text: &str, budget: usize) -> String {
+    let mut used = 0;
+    let mut kept: Vec<&str> = Vec::new();
+    for line in text.lines().rev() {
+        used += estimate_tokens(line);
+        if used > budget && !kept.is_empty() {
+            break;
+        }
+        kept.push(line);
+    }
+    kept.reverse();
+    kept.join("\n")
+}
+
+fn token_budget(app: &AppHandle) -> usize {
+    settings::get::<usize>(app, "context_token_budget")
+        .filter(|b| *b >= 100)
+        .unwrap_or(DEFAULT_TOKEN_BUDGET)
+}
+
+/// Rough count without a tokenizer: about four characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn transcript_line(seg: &StoredSegment) -> String {
+    format!("{}: {}", capitalize(&seg.speaker), seg.text.trim())
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` lines of about `tokens` tokens each.
+    fn lines(count: usize, tokens: usize) -> Vec<String> {
+        (0..count).map(|i| format!("{:0width$}", i, width = tokens * 4)).collect()
+    }
+
+    fn segment(speaker: &str, text: &str) -> StoredSegment {
+        StoredSegment { id: 1, speaker: speaker.to_string(), text: text.to_string(), start_ms: 0, end_ms: 0, confidence: None }
+    }
+
+    #[test]
+    fn transcript_within_budget_stays_verbatim() {
+        assert_eq!(split(&lines(10, 50), 1000, false), 0);
+        assert_eq!(split(&[], 1000, false), 0);
+    }
+
+    #[test]
+    fn small_overflow_waits_for_a_fold_worth_running() {
+        // 700 tokens against 600 verbatim: 100 over, below the 150 fold share.
+        assert_eq!(split(&lines(14, 50), 1000, false), 0);
+        // While a fold runs, the budget holds anyway.
+        assert_eq!(split(&lines(14, 50), 1000, true), 2);
+    }
+
+    #[test]
+    fn large_overflow_keeps_the_newest_lines_within_the_verbatim_share() {
+        let lines = lines(40, 50);
+        let keep_from = split(&lines, 1000, false);
+        assert_eq!(keep_from, 28);
+        let verbatim: usize = lines[keep_from..].iter().map(|l| estimate_tokens(l)).sum();
+        assert!(verbatim <= 600);
+    }
+
+    #[test]
+    fn the_last_line_is_kept_even_over_budget() {
+        let mut lines = lines(5, 50);
+        lines.push("x".repeat(4000));
+        assert_eq!(split(&lines, 1000, false), 5);
+    }
+
+    #[test]
+    fn keep_last_lines_keeps_the_newest_that_fit() {
+        assert_eq!(keep_last_lines("aaaa\nbbbb\ncccc", 2), "bbbb\ncccc");
+        assert_eq!(keep_last_lines("aaaa\nbbbb", 10), "aaaa\nbbbb");
+        // The newest line is kept even when it alone is too long.
+        assert_eq!(keep_last_lines("aaaa\nbbbbbbbbbbbb", 2), "bbbbbbbbbbbb");
+        assert_eq!(keep_last_lines("", 10), "");
+    }
+
+    #[test]
+    fn local_summary_prefers_the_question() {
+        let segments = [
+            segment("interviewer", "Thanks. How would you shard it? Take your time."),
+            segment("me", "I would hash the key. Then rebalance."),
+            segment("me", "   "),
+        ];
+        assert_eq!(summarize_locally(&segments), "- Interviewer: How would you shard it?\n- Me: I would hash the key.");
+    }
+}
@@ -0,0 +1,302 @@
+use crate::sessions::{SessionDetail, StoredSegment};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Srt,
+    Vtt,
+    Markdown,
+    Json,
+}
+
+/// How the speaker is shown in front of each cue / paragraph.
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeakerLabel {
+    /// No speaker label.
+    None,
+    /// `Interviewer: text`
+    #[default]
+    Prefix,
+    /// `[Interviewer] text`
+    Bracketed,
+    /// WebVTT voice span `<v Interviewer>text`; behaves like `Prefix` elsewhere.
+    Voice,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub speaker_label: SpeakerLabel,
+    /// Display names for raw speaker IDs, e.g. `{"interviewer": "Alice"}`.
+    /// Unmapped speakers are shown capitalised.
+    pub speaker_names: HashMap<String, String>,
+    /// Join consecutive segments from the same speaker into one cue.
+    pub merge_consecutive: bool,
+    /// Largest silence between two segments that may still be merged.
+    pub merge_gap_ms: i64,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            speaker_label: SpeakerLabel::default(),
+            speaker_names: HashMap::new(),
+            merge_consecutive: false,
+            merge_gap_ms: 2000,
+        }
+    }
+}
+
+/// Render a stored session in the requested format. Subtitle timestamps are
+/// relative to the session start, which is when audio capture began.
+pub fn render(detail: &SessionDetail, format: ExportFormat, options: &ExportOptions) -> Result<String, String> {
+    Ok(match format {
+        ExportFormat::Srt => render_srt(&cues(detail, options), options),
+        ExportFormat::Vtt => render_vtt(&cues(detail, options), options),
+        ExportFormat::Markdown => render_markdown(detail, &cues(detail, options), options),
+        // Lossless: every stored field, no merging or relabelling.
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(detail).map_err(|e| format!("Failed to serialize session: {}", e))?
+        }
+    })
+}
+
+pub fn file_extension(format: ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::Srt => "srt",
+        ExportFormat::Vtt => "vtt",
+        ExportFormat::Markdown => "md",
+        ExportFormat::Json => "json",
+    }
+}
+
+/// Segments with text, merged per `options`. Empty ones would become blank cues.
+fn cues(detail: &SessionDetail, options: &ExportOptions) -> Vec<StoredSegment> {
+    let spoken: Vec<StoredSegment> = detail.segments.iter().filter(|s| !s.text.trim().is_empty()).cloned().collect();
+    if options.merge_consecutive {
+        merge_segments(&spoken, options.merge_gap_ms)
+    } else {
+        spoken
+    }
+}
+
+fn merge_segments(segments: &[StoredSegment], max_gap_ms: i64) -> Vec<StoredSegment> {
+    let mut merged: Vec<StoredSegment> = Vec::new();
+    for seg in segments {
+        if let Some(last) = merged.last_mut() {
+            if last.speaker == seg.speaker && seg.start_ms - last.end_ms <= max_gap_ms {
+                last.text = format!("{} {}", last.text.trim_end(), seg.text.trim_start());
+                last.end_ms = last.end_ms.max(seg.end_ms);
+                last.confidence = match (last.confidence, seg.confidence) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                continue;
+            }
+        }
+        merged.push(seg.clone());
+    }
+    merged
+}
+
+fn display_name(speaker: &str, options: &ExportOptions) -> String {
+    if let Some(name) = options.speaker_names.get(speaker) {
+        return name.clone();
+    }
+    let mut chars = speaker.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => "Unknown".to_string(),
+    }
+}
+
+fn labelled(text: &str, name: &str, label: SpeakerLabel) -> String {
+    match label {
+        SpeakerLabel::None => text.to_string(),
+        SpeakerLabel::Prefix | SpeakerLabel::Voice => format!("{}: {}", name, text),
+        SpeakerLabel::Bracketed => format!("[{}] {}", name, text),
+    }
+}
+
+/// Cue end, never before (or at) the start — zero-length cues are dropped by players.
+fn cue_end(seg: &StoredSegment) -> i64 {
+    seg.end_ms.max(seg.start_ms + 1)
+}
+
+fn timestamp(ms: i64, fraction_sep: char) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        fraction_sep,
+        ms % 1000
+    )
+}
+
+fn render_srt(segments: &[StoredSegment], options: &ExportOptions) -> String {
+    let mut out = String::new();
+    for (i, seg) in segments.iter().enumerate() {
+        let _ = write!(
+            out,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            timestamp(seg.start_ms, ','),
+            timestamp(cue_end(seg), ','),
+            labelled(seg.text.trim(), &display_name(&seg.speaker, options), options.speaker_label)
+        );
+    }
+    out
+}
+
+fn escape_vtt(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn render_vtt(segments: &[StoredSegment], options: &ExportOptions) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for (i, seg) in segments.iter().enumerate() {
+        let text = escape_vtt(seg.text.trim());
+        let name = escape_vtt(&display_name(&seg.speaker, options));
+        let body = match options.speaker_label {
+            SpeakerLabel::Voice => format!("<v {}>{}", name, text),
+            label => labelled(&text, &name, label),
+        };
+        let _ = write!(
+            out,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            timestamp(seg.start_ms, '.'),
+            timestamp(cue_end(seg), '.'),
+            body
+        );
+    }
+    out
+}
+
+fn render_markdown(detail: &SessionDetail, segments: &[StoredSegment], options: &ExportOptions) -> String {
+    let session = &detail.session;
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", session.title);
+    if let Some(started) = chrono::DateTime::from_timestamp_millis(session.started_at) {
+        let started = started.with_timezone(&chrono::Local);
+        let _ = write!(out, "_Recorded {}", started.format("%Y-%m-%d %H:%M"));
+        if let Some(ended) = session.ended_at {
+            let _ = write!(out, " · {} min", (ended - session.started_at).max(0) / 60_000);
+        }
+        out.push_str("_\n\n");
+    }
+
+    let mut current: Option<&str> = None;
+    for seg in segments {
+        let text = seg.text.trim();
+        // A heading whenever the speaker changes; labels are folded into it.
+        if current != Some(seg.speaker.as_str()) {
+            match options.speaker_label {
+                SpeakerLabel::None => {
+                    let _ = writeln!(out, "### {}\n", timestamp(seg.start_ms, '.'));
+                }
+                _ => {
+                    let _ = writeln!(
+                        out,
+                        "### {} · {}\n",
+                        display_name(&seg.speaker, options),
+                        timestamp(seg.start_ms, '.')
+                    );
+                }
+            }
+            current = Some(seg.speaker.as_str());
+        }
+        let _ = writeln!(out, "{}\n", text);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sessions::SessionSummary;
+
+    fn segment(id: i64, speaker: &str, text: &str, start_ms: i64, end_ms: i64) -> StoredSegment {
+        StoredSegment { id, speaker: speaker.to_string(), text: text.to_string(), start_ms, end_ms, confidence: Some(0.9) }
+    }
+
+    fn detail(segments: Vec<StoredSegment>) -> SessionDetail {
+        SessionDetail {
+            session: SessionSummary {
+                id: 1,
+                title: "Interview".to_string(),
+                started_at: 0,
+                ended_at: None,
+                devices: serde_json::Value::Null,
+                settings: serde_json::Value::Null,
+                segment_count: segments.len() as i64,
+                exchange_count: 0,
+                screenshot_count: 0,
+            },
+            segments,
+            exchanges: Vec::new(),
+            screenshots: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn timestamps_use_the_format_separator() {
+        assert_eq!(timestamp(0, ','), "00:00:00,000");
+        assert_eq!(timestamp(3_723_456, '.'), "01:02:03.456");
+        assert_eq!(timestamp(-5, ','), "00:00:00,000");
+    }
+
+    #[test]
+    fn merging_joins_close_segments_of_one_speaker() {
+        let mut segments = vec![
+            segment(1, "interviewer", "Tell me ", 0, 1000),
+            segment(2, "interviewer", " about yourself.", 1500, 3000),
+            segment(3, "interviewer", "Take your time.", 9000, 10000),
+            segment(4, "me", "Sure.", 10500, 11000),
+        ];
+        segments[1].confidence = Some(0.5);
+        let merged = merge_segments(&segments, 2000);
+        let texts: Vec<&str> = merged.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["Tell me about yourself.", "Take your time.", "Sure."]);
+        assert_eq!((merged[0].start_ms, merged[0].end_ms), (0, 3000));
+        assert_eq!(merged[0].confidence, Some(0.5));
+    }
+
+    #[test]
+    fn empty_segments_produce_no_cues() {
+        let detail = detail(vec![
+            segment(1, "interviewer", "Hello", 0, 1000),
+            segment(2, "interviewer", "  ", 1000, 2000),
+            segment(3, "me", "Hi", 2000, 3000),
+        ]);
+        let srt = render(&detail, ExportFormat::Srt, &ExportOptions::default()).unwrap();
+        assert_eq!(
+            srt,
+            "1\n00:00:00,000 --> 00:00:01,000\nInterviewer: Hello\n\n2\n00:00:02,000 --> 00:00:03,000\nMe: Hi\n\n"
+        );
+    }
+
+    #[test]
+    fn vtt_escapes_speaker_names_in_every_label_style() {
+        let detail = detail(vec![segment(1, "interviewer", "a < b", 0, 1000)]);
+        let mut options = ExportOptions::default();
+        options.speaker_names.insert("interviewer".to_string(), "R&D <lead>".to_string());
+        for (label, expected) in [
+            (SpeakerLabel::Prefix, "R&amp;D &lt;lead&gt;: a &lt; b"),
+            (SpeakerLabel::Bracketed, "[R&amp;D &lt;lead&gt;] a &lt; b"),
+            (SpeakerLabel::Voice, "<v R&amp;D &lt;lead&gt;>a &lt; b"),
+        ] {
+            options.speaker_label = label;
+            let vtt = render(&detail, ExportFormat::Vtt, &options).unwrap();
+            assert_eq!(vtt, format!("WEBVTT\n\n1\n00:00:00.000 --> 00:00:01.000\n{}\n\n", expected));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Modifiers, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::settings;
+
+/// Something a global shortcut can trigger. Only `toggle_window` is handled
+/// here; the others are emitted as `hotkey` events for the frontend.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Show, restore or hide the main window.
+    ToggleWindow,
+    /// Start or stop interview capture.
+    ToggleCapture,
+    /// Capture the screen and send it to the assistant.
+    Screenshot,
+    /// Capture the `screenshot_region` area and send it to the assistant.
+    CaptureRegion,
+    /// Ask the assistant about the current context (transcript, notes, summary).
+    SendContext,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::ToggleWindow,
+        Action::ToggleCapture,
+        Action::Screenshot,
+        Action::CaptureRegion,
+        Action::SendContext,
+    ];
+
+    fn default_accelerator(self) -> &'static str {
+        match self {
+            Action::ToggleWindow => "Alt+1",
+            Action::ToggleCapture => "Alt+2",
+            Action::Screenshot => "Alt+3",
+            Action::CaptureRegion => "Alt+4",
+            Action::SendContext => "Alt+5",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Action::ToggleWindow => "toggle_window",
+            Action::ToggleCapture => "toggle_capture",
+            Action::Screenshot => "screenshot",
+            Action::CaptureRegion => "capture_region",
+            Action::SendContext => "send_context",
+        }
+    }
+}
+
+/// One action's shortcut and whether it is active.
+#[derive(Serialize, Clone, Debug)]
+pub struct Binding {
+    pub action: Action,
+    /// `None` when the action is unbound.
+    pub accelerator: Option<String>,
+    pub registered: bool,
+    /// Why the shortcut isn't registered: invalid, bound twice, or already
+    /// taken by another application.
+    pub error: Option<String>,
+}
+
+/// Emitted as `hotkey` for actions the frontend carries out.
+#[derive(Serialize, Clone, Debug)]
+pub struct HotkeyEvent {
+    pub action: Action,
+}
+
+#[derive(Default)]
+struct Registry {
+    /// Registered shortcut IDs and what they trigger.
+    actions: HashMap<u32, Action>,
+    bindings: Vec<Binding>,
+}
+
+/// Managed state: the shortcuts currently registered with the OS.
+pub struct Hotkeys(Mutex<Registry>);
+
+impl Hotkeys {
+    pub fn new() -> Self {
+        Hotkeys(Mutex::new(Registry::default()))
+    }
+}
+
+/// Install the global shortcut plugin and register the configured bindings.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    app.plugin(tauri_plugin_global_shortcut::Builder::new().with_handler(dispatch).build())
+        .map_err(|e| format!("Failed to install global shortcut plugin: {}", e))?;
+    apply(app);
+    Ok(())
+}
+
+/// Parse an accelerator such as `Ctrl+Shift+S`. Shortcuts must use Ctrl, Alt
+/// or Super, or be a function key, so they don't swallow ordinary typing.
+pub fn validate(accelerator: &str) -> Result<Shortcut, String> {
+    let shortcut: Shortcut = accelerator
+        .parse()
+        .map_err(|e| format!("Invalid shortcut {}: {}", accelerator, e))?;
+    let key = shortcut.key.to_string();
+    let function_key = key.strip_prefix('F').is_some_and(|n| n.parse::<u8>().is_ok());
+    if !shortcut.mods.intersects(Modifiers::CONTROL | Modifiers::ALT | Modifiers::SUPER) && !function_key {
+        return Err(format!("{} needs Ctrl, Alt or Super, or a function key", accelerator));
+    }
+    Ok(shortcut)
+}
+
+/// The configured accelerator for each action: the `hotkeys` setting, where
+/// `null` unbinds an action, over the defaults.
+fn configured(app: &AppHandle) -> Vec<(Action, Option<String>)> {
+    let overrides: HashMap<Action, Option<String>> = settings::get(app, "hotkeys").unwrap_or_default();
+    Action::ALL
+        .iter()
+        .map(|&action| {
+            let accelerator = match overrides.get(&action) {
+                Some(accelerator) => accelerator.clone(),
+                None => Some(action.default_accelerator().to_string()),
+            };
+            (action, accelerator)
+        })
+        .collect()
+}
+
+/// Re-register every binding with the OS. A binding that fails doesn't stop
+/// the others; its error is kept for `bindings`.
+pub fn apply(app: &AppHandle) -> Vec<Binding> {
+    let global = app.global_shortcut();
+    if let Err(e) = global.unregister_all() {
+        eprintln!("[Hotkey] ✗ Failed to unregister shortcuts: {}", e);
+    }
+
+    let mut actions: HashMap<u32, Action> = HashMap::new();
+    let mut bindings = Vec::new();
+    for (action, accelerator) in configured(app) {
+        let Some(accelerator) = accelerator else {
+            bindings.push(Binding { action, accelerator: None, registered: false, error: None });
+            continue;
+        };
+        let result = validate(&accelerator).and_then(|shortcut| {
+            if let Some(other) = actions.get(&shortcut.id()) {
+                return Err(format!("{} is already bound to {}", accelerator, other.name()));
+            }
+            global
+                .register(shortcut)
+                .map_err(|e| format!("{} is taken by another application: {}", accelerator, e))?;
+            actions.insert(shortcut.id(), action);
+            Ok(())
+        });
+        match &result {
+            Ok(()) => println!("[Hotkey] ✓ {} → {}", accelerator, action.name()),
+            Err(e) => eprintln!("[Hotkey] ✗ {}: {}", action.name(), e),
+        }
+        bindings.push(Binding {
+            action,
+            accelerator: Some(accelerator),
+            registered: result.is_ok(),
+            error: result.err(),
+        });
+    }
+
+    let state = app.state::<Hotkeys>();
+    let mut registry = state.0.lock().unwrap();
+    registry.actions = actions;
+    registry.bindings = bindings.clone();
+    bindings
+}
+
+/// Current bindings with their registration status.
+pub fn bindings(app: &AppHandle) -> Vec<Binding> {
+    app.state::<Hotkeys>().0.lock().unwrap().bindings.clone()
+}
+
+/// Bind `action` to `accelerator`, or unbind it with `None`, then
+/// re-register. Invalid shortcuts and ones already bound to another action
+/// are rejected without saving; OS conflicts are reported in the result.
+pub fn set(app: &AppHandle, action: Action, accelerator: Option<String>) -> Result<Vec<Binding>, String> {
+    let accelerator = accelerator.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+    if let Some(accelerator) = &accelerator {
+        let id = validate(accelerator)?.id();
+        for (other, current) in configured(app) {
+            let taken = current.as_deref().and_then(|c| validate(c).ok()).is_some_and(|s| s.id() == id);
+            if other != action && taken {
+                return Err(format!("{} is already bound to {}", accelerator, other.name()));
+            }
+        }
+    }
+
+    let mut overrides: HashMap<Action, Option<String>> = settings::get(app, "hotkeys").unwrap_or_default();
+    overrides.insert(action, accelerator);
+    settings::set(app, "hotkeys", &overrides)?;
+    Ok(apply(app))
+}
+
+/// Restore the default bindings.
+pub fn reset(app: &AppHandle) -> Result<Vec<Binding>, String> {
+    settings::remove(app, "hotkeys")?;
+    Ok(apply(app))
+}
+
+fn dispatch(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let Some(action) = app.state::<Hotkeys>().0.lock().unwrap().actions.get(&shortcut.id()).copied() else {
+        return;
+    };
+    match action {
+        Action::ToggleWindow => toggle_window(app),
+        _ => {
+            let _ = app.emit("hotkey", HotkeyEvent { action });
+        }
+    }
+}
+
+fn toggle_window(app: &AppHandle) {
+    let Some(win) = app.get_webview_window("main") else { return };
+    if win.is_minimized().unwrap_or(false) {
+        // Window is minimized — restore it
+        let _ = win.unminimize();
+        let _ = win.show();
+        let _ = win.set_focus();
+    } else if win.is_visible().unwrap_or(false) {
+        let _ = win.hide();
+    } else {
+        let _ = win.show();
+        let _ = win.set_focus();
+    }
+}
@@ -1,15 +1,63 @@
 use tauri::{Manager, Runtime, Window, State};
 use window_vibrancy::apply_blur;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 mod audio;
-use audio::{InterviewStreams, DeviceInfo};
+mod autocapture;
+mod clipboard;
+mod code;
+mod context;
+mod export;
+mod hotkeys;
+mod llm;
+mod ocr;
+mod prompts;
+mod redact;
+mod screen;
+mod search;
+mod sessions;
+mod settings;
+mod transcribe;
+mod vault;
+use audio::{InterviewStreams, DeviceInfo, ProbeReport};
+use autocapture::{AutoCapture, AutoCaptureOptions};
+use clipboard::ClipboardWatcher;
+use context::{Context, ContextManager};
+use export::{ExportFormat, ExportOptions};
+use hotkeys::{Action, Binding, Hotkeys};
+use llm::{LlmClient, LlmRequest, LlmResponse, ModelInfo};
+use llm::cache::ResponseCache;
+use llm::stream::LlmStreams;
+use llm::usage::{Price, PriceEntry, UsageReport};
+use ocr::LocalOcr;
+use prompts::{PromptStore, PromptSummary, PromptTemplate, RenderedPrompt};
+use screen::{CaptureSource, DisplayInfo, DisplaySelector, EncodeOptions, Region, Screenshot, WindowInfo};
+use search::{SearchQuery, SearchHit};
+use redact::{Redacted, RedactionStatus};
+use sessions::{RedactionEntry, ScreenshotImage, SessionStore, SessionSummary, SessionDetail, StoredScreenshot};
+use transcribe::LocalTranscriber;
+use vault::{Vault, VaultStatus};
 
 #[tauri::command]
 fn get_audio_devices() -> Vec<DeviceInfo> {
     audio::get_audio_devices()
 }
 
+/// Capture a few seconds from a device (default 3 s, at most 10 s) and report
+/// the negotiated config, levels and a short preview clip.
+#[tauri::command]
+async fn probe_audio_device(
+    device_name: Option<String>,
+    duration_secs: Option<f32>,
+) -> Result<ProbeReport, String> {
+    let secs = duration_secs.unwrap_or(3.0).clamp(1.0, 10.0);
+    let duration = std::time::Duration::from_secs_f32(secs);
+    tauri::async_runtime::spawn_blocking(move || audio::probe_device(device_name, duration))
+        .await
+        .map_err(|e| format!("Probe task failed: {}", e))?
+}
+
 #[tauri::command]
 fn set_always_on_top<R: Runtime>(window: Window<R>, enabled: bool) {
     window.set_always_on_top(enabled).unwrap();
@@ -69,12 +117,21 @@ fn toggle_screen_share_protection<R: Runtime>(window: Window<R>, enabled: bool)
 fn start_interview_mode(
     app: tauri::AppHandle, 
     state: State<'_, InterviewStreams>,
-    interviewer_device: Option<String>
+    sessions: State<'_, SessionStore>,
+    interviewer_device: Option<String>,
+    settings: Option<serde_json::Value>,
 ) {
     println!("═══════════════════════════════════════════");
     println!("[Interview] Starting Interview Mode (interviewer only)");
     println!("[Interview] Interviewer device: {:?}", interviewer_device);
     println!("═══════════════════════════════════════════");
+
+    // Every interview run is recorded as its own session.
+    let _ = sessions.end();
+    let devices = vec![interviewer_device.clone().unwrap_or_else(|| "Default Output".to_string())];
+    if let Err(e) = sessions.begin(&devices, &settings.unwrap_or_else(|| serde_json::json!({}))) {
+        eprintln!("[Interview] ✗ {}", e);
+    }
     
     let mut streams_vec = Vec::new();
     
@@ -98,51 +155,391 @@ fn start_interview_mode(
 
 
 #[tauri::command]
-fn stop_interview_mode(state: State<'_, InterviewStreams>) {
+fn stop_interview_mode(state: State<'_, InterviewStreams>, sessions: State<'_, SessionStore>) {
     println!("Stopping Interview Mode...");
     let mut streams = state.0.lock().unwrap();
     *streams = None; 
+    if let Err(e) = sessions.end() {
+        eprintln!("[Interview] ✗ {}", e);
+    }
 }
 
 #[tauri::command]
-async fn capture_screenshot<R: Runtime>(_window: Window<R>) -> Result<String, String> {
-    use base64::{Engine as _, engine::general_purpose};
-    use screenshots::Screen;
-    use image::{ImageEncoder, codecs::png::PngEncoder, ExtendedColorType};
-    
-    let screens = Screen::all();
-    
-    if screens.is_empty() {
-        return Err("No screens found".to_string());
-    }
-    
-    let screen = &screens[0];
-    let screenshot_image = screen.capture()
-        .ok_or("Failed to capture screenshot")?;
-    
-    let buffer = screenshot_image.buffer();
-    let width = screenshot_image.width();
-    let height = screenshot_image.height();
-    let expected_rgba_size = (width * height * 4) as usize;
-    
-    let png_data = if buffer.len() < expected_rgba_size {
-        buffer.to_vec()
-    } else {
-        let mut png_vec = Vec::new();
-        {
-            let encoder = PngEncoder::new(&mut png_vec);
-            encoder.write_image(
-                &buffer,
-                width,
-                height,
-                ExtendedColorType::Rgba8,
-            ).map_err(|e| format!("Failed to encode PNG: {}", e))?;
+fn list_sessions(sessions: State<'_, SessionStore>) -> Result<Vec<SessionSummary>, String> {
+    sessions.list()
+}
+
+#[tauri::command]
+fn open_session(sessions: State<'_, SessionStore>, id: i64) -> Result<SessionDetail, String> {
+    sessions.open_session(id)
+}
+
+#[tauri::command]
+fn rename_session(sessions: State<'_, SessionStore>, id: i64, title: String) -> Result<(), String> {
+    sessions.rename(id, &title)
+}
+
+#[tauri::command]
+fn delete_session(sessions: State<'_, SessionStore>, id: i64) -> Result<(), String> {
+    sessions.delete(id)
+}
+
+/// Render a session's transcript as SRT, WebVTT, Markdown or JSON. When `path`
+/// is given the result is also written there.
+#[tauri::command]
+fn export_session(
+    sessions: State<'_, SessionStore>,
+    id: i64,
+    format: ExportFormat,
+    options: Option<ExportOptions>,
+    path: Option<String>,
+) -> Result<String, String> {
+    let detail = sessions.open_session(id)?;
+    let rendered = export::render(&detail, format, &options.unwrap_or_default())?;
+    if let Some(path) = path {
+        let mut path = std::path::PathBuf::from(path);
+        if path.extension().is_none() {
+            path.set_extension(export::file_extension(format));
         }
-        png_vec
-    };
-    
-    let base64_image = general_purpose::STANDARD.encode(&png_data);
-    Ok(base64_image)
+        std::fs::write(&path, &rendered).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        println!("[Export] Session {} written to {}", id, path.display());
+    }
+    Ok(rendered)
+}
+
+/// Stored screenshots, newest first: one session's or, without `session_id`,
+/// all of them. `limit` defaults to 100.
+#[tauri::command]
+fn list_screenshots(
+    sessions: State<'_, SessionStore>,
+    session_id: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<StoredScreenshot>, String> {
+    sessions.list_screenshots(session_id, limit.unwrap_or(100))
+}
+
+/// A stored screenshot as base64, or its thumbnail.
+#[tauri::command]
+fn get_screenshot(sessions: State<'_, SessionStore>, id: i64, thumbnail: Option<bool>) -> Result<ScreenshotImage, String> {
+    sessions.screenshot_image(id, thumbnail.unwrap_or(false))
+}
+
+#[tauri::command]
+fn delete_screenshot(sessions: State<'_, SessionStore>, id: i64) -> Result<(), String> {
+    sessions.delete_screenshot(id)
+}
+
+/// Copy screenshots and their OCR text into `dir`; returns the written paths.
+#[tauri::command]
+fn export_screenshots(sessions: State<'_, SessionStore>, ids: Vec<i64>, dir: String) -> Result<Vec<String>, String> {
+    sessions.export_screenshots(&ids, std::path::Path::new(&dir))
+}
+
+/// Show what the redaction rules would replace in `text`, without logging.
+#[tauri::command]
+fn preview_redaction(app: tauri::AppHandle, text: String) -> Redacted {
+    redact::text(&app, &text)
+}
+
+/// Whether redaction is on and screenshots can be masked.
+#[tauri::command]
+fn get_redaction_status(app: tauri::AppHandle) -> RedactionStatus {
+    redact::status(&app)
+}
+
+/// Redaction audit log, newest first: one session's or, without
+/// `session_id`, all of it. `limit` defaults to 200.
+#[tauri::command]
+fn get_redaction_log(
+    sessions: State<'_, SessionStore>,
+    session_id: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<RedactionEntry>, String> {
+    sessions.redaction_log(session_id, limit.unwrap_or(200))
+}
+
+/// Full-text search over stored transcripts, assistant exchanges and
+/// screenshot text, best matches first.
+#[tauri::command]
+fn search_sessions(sessions: State<'_, SessionStore>, query: SearchQuery) -> Result<Vec<SearchHit>, String> {
+    sessions.search(&query)
+}
+
+/// Send a request to the configured model provider. When `request.kind` is
+/// set, the exchange is also stored in the active session.
+#[tauri::command]
+async fn llm_generate(
+    app: tauri::AppHandle,
+    client: State<'_, LlmClient>,
+    mut request: LlmRequest,
+) -> Result<LlmResponse, String> {
+    prompts::apply(&app, &mut request)?;
+    let response = client.generate(&app, &request).await.map_err(|e| e.to_string())?;
+    llm::record_exchange(&app, &request, &response);
+    Ok(response)
+}
+
+/// Start a streaming request; see `llm::stream` for the events it emits.
+#[tauri::command]
+fn llm_stream(app: tauri::AppHandle, request_id: String, mut request: LlmRequest) -> Result<(), String> {
+    prompts::apply(&app, &mut request)?;
+    llm::stream::start(&app, request_id, request)
+}
+
+#[tauri::command]
+fn llm_cancel(app: tauri::AppHandle, request_id: String) -> bool {
+    llm::stream::cancel(&app, &request_id)
+}
+
+#[tauri::command]
+async fn llm_list_models(app: tauri::AppHandle, client: State<'_, LlmClient>) -> Result<Vec<ModelInfo>, String> {
+    client.list_models(&app).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn vault_status(vault: State<'_, Vault>) -> VaultStatus {
+    vault.status()
+}
+
+/// Unlock the vault with a passphrase (or set one up when there is no keyring).
+#[tauri::command]
+async fn vault_unlock(app: tauri::AppHandle, passphrase: String) -> Result<VaultStatus, String> {
+    // Argon2 takes a noticeable moment; keep it off the async runtime.
+    tauri::async_runtime::spawn_blocking(move || {
+        let vault = app.state::<Vault>();
+        vault.unlock(&passphrase)?;
+        vault::migrate_plaintext_key(&app);
+        Ok(vault.status())
+    })
+    .await
+    .map_err(|e| format!("Vault task failed: {}", e))?
+}
+
+/// Replace a vault that can't be unlocked with an empty one. Stored
+/// credentials are lost; the old file is kept as a backup. An unlocked vault
+/// is refused unless `force` is set.
+#[tauri::command]
+fn reset_vault(vault: State<'_, Vault>, force: Option<bool>) -> Result<VaultStatus, String> {
+    vault.reset(force.unwrap_or(false))?;
+    Ok(vault.status())
+}
+
+/// Store a provider credential (`gemini`, `openai`). The secret is write-only:
+/// nothing returns it to the frontend.
+#[tauri::command]
+fn set_credential(vault: State<'_, Vault>, name: String, secret: String) -> Result<VaultStatus, String> {
+    vault.set(&name, &secret)?;
+    Ok(vault.status())
+}
+
+#[tauri::command]
+fn delete_credential(vault: State<'_, Vault>, name: String) -> Result<VaultStatus, String> {
+    vault.delete(&name)?;
+    Ok(vault.status())
+}
+
+/// Check a stored credential by listing the provider's models. Returns the model count.
+#[tauri::command]
+async fn test_credential(app: tauri::AppHandle, client: State<'_, LlmClient>, name: String) -> Result<usize, String> {
+    let provider = client.build_provider(&app, &name).map_err(|e| e.to_string())?;
+    let models = provider.list_models().await.map_err(|e| e.to_string())?;
+    Ok(models.len())
+}
+
+/// Token and cost totals per session, day and provider, with the budget status
+/// and response cache stats.
+#[tauri::command]
+fn get_usage(
+    app: tauri::AppHandle,
+    sessions: State<'_, SessionStore>,
+    cache: State<'_, ResponseCache>,
+) -> Result<UsageReport, String> {
+    let mut report = sessions.usage_report(llm::usage::budget_status(&app))?;
+    report.cache = Some(cache.stats(&app));
+    Ok(report)
+}
+
+/// Delete every cached model response.
+#[tauri::command]
+fn clear_llm_cache(cache: State<'_, ResponseCache>) {
+    cache.clear();
+}
+
+#[tauri::command]
+fn get_prices(app: tauri::AppHandle) -> Vec<PriceEntry> {
+    llm::usage::prices(&app)
+}
+
+/// Set a model's price in USD per million tokens; `None` restores the built-in one.
+#[tauri::command]
+fn set_price(app: tauri::AppHandle, model: String, price: Option<Price>) -> Result<Vec<PriceEntry>, String> {
+    llm::usage::set_price(&app, &model, price)
+}
+
+/// The transcript context the next model request would get.
+#[tauri::command]
+fn get_context(app: tauri::AppHandle) -> Context {
+    context::build(&app)
+}
+
+#[tauri::command]
+fn list_prompts(prompts: State<'_, PromptStore>) -> Result<Vec<PromptSummary>, String> {
+    prompts.list()
+}
+
+/// A template with its full version history.
+#[tauri::command]
+fn get_prompt(prompts: State<'_, PromptStore>, name: String) -> Result<PromptTemplate, String> {
+    prompts.get(&name)
+}
+
+#[tauri::command]
+fn save_prompt(
+    prompts: State<'_, PromptStore>,
+    name: String,
+    body: String,
+    description: Option<String>,
+) -> Result<PromptTemplate, String> {
+    prompts.save(&name, &body, description)
+}
+
+#[tauri::command]
+fn reset_prompt(prompts: State<'_, PromptStore>, name: String) -> Result<PromptTemplate, String> {
+    prompts.reset(&name)
+}
+
+#[tauri::command]
+fn delete_prompt(prompts: State<'_, PromptStore>, name: String) -> Result<(), String> {
+    prompts.delete(&name)
+}
+
+/// Render a template with the current context. Pass `body` to preview unsaved edits.
+#[tauri::command]
+fn preview_prompt(
+    app: tauri::AppHandle,
+    name: String,
+    variables: Option<HashMap<String, String>>,
+    body: Option<String>,
+) -> Result<RenderedPrompt, String> {
+    let variables = variables.unwrap_or_default();
+    match body {
+        Some(body) => Ok(prompts::render_body(&app, &name, 0, &body, &variables)),
+        None => prompts::render_named(&app, &name, &variables),
+    }
+}
+
+/// Start the offline Whisper transcriber. `model` is a ggml model path or a
+/// file name in `<app data>/models`; `language` defaults to auto-detection.
+#[tauri::command]
+async fn start_local_transcription(
+    app: tauri::AppHandle,
+    model: Option<String>,
+    language: Option<String>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || transcribe::start(&app, model, language))
+        .await
+        .map_err(|e| format!("Transcriber task failed: {}", e))?
+}
+
+#[tauri::command]
+fn stop_local_transcription(app: tauri::AppHandle) {
+    transcribe::stop(&app);
+}
+
+#[tauri::command]
+fn read_clipboard_text() -> Result<Option<String>, String> {
+    clipboard::read_text()
+}
+
+/// Run a copied image through the screenshot pipeline (OCR, encoding, session
+/// history). `None` when the clipboard holds no image.
+#[tauri::command]
+async fn read_clipboard_image(
+    app: tauri::AppHandle,
+    encoding: Option<EncodeOptions>,
+    ocr: Option<bool>,
+) -> Result<Option<Screenshot>, String> {
+    tauri::async_runtime::spawn_blocking(move || match clipboard::read_image()? {
+        Some(image) => screen::process(&app, image, None, encoding.unwrap_or_default(), ocr).map(Some),
+        None => Ok(None),
+    })
+    .await
+    .map_err(|e| format!("Clipboard task failed: {}", e))?
+}
+
+/// Emit `clipboard-changed` whenever new text or an image is copied.
+#[tauri::command]
+fn start_clipboard_watcher(app: tauri::AppHandle) -> Result<(), String> {
+    clipboard::start(&app)
+}
+
+#[tauri::command]
+fn stop_clipboard_watcher(app: tauri::AppHandle) {
+    clipboard::stop(&app);
+}
+
+/// Sample the screen periodically and keep only changed captures, emitted as
+/// `screen-changed` events.
+#[tauri::command]
+fn start_auto_capture(app: tauri::AppHandle, options: Option<AutoCaptureOptions>) -> Result<(), String> {
+    autocapture::start(&app, options.unwrap_or_default())
+}
+
+#[tauri::command]
+fn stop_auto_capture(app: tauri::AppHandle) {
+    autocapture::stop(&app);
+}
+
+#[tauri::command]
+fn list_displays() -> Vec<DisplayInfo> {
+    screen::list_displays()
+}
+
+/// Top-level windows that `capture_screenshot` can capture (Windows only).
+#[tauri::command]
+fn list_windows() -> Result<Vec<WindowInfo>, String> {
+    screen::list_windows()
+}
+
+/// Capture a display, or the window `window` from `list_windows`, and encode
+/// it. `display` is a display ID or `primary`, `cursor` or `all`; it defaults
+/// to the `screenshot_display` setting. `region` crops the capture, in its
+/// physical pixels. `encoding` overrides the `screenshot_*` encoding settings,
+/// and `ocr` the `screenshot_ocr` setting.
+#[tauri::command]
+async fn capture_screenshot(
+    app: tauri::AppHandle,
+    display: Option<DisplaySelector>,
+    window: Option<i64>,
+    region: Option<Region>,
+    encoding: Option<EncodeOptions>,
+    ocr: Option<bool>,
+) -> Result<Screenshot, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let source = CaptureSource { display, window, region };
+        let image = screen::grab(&app, &source)?;
+        screen::process(&app, image, Some(&source), encoding.unwrap_or_default(), ocr)
+    })
+    .await
+    .map_err(|e| format!("Screenshot task failed: {}", e))?
+}
+
+/// Global shortcut bindings and whether each is registered.
+#[tauri::command]
+fn get_hotkeys(app: tauri::AppHandle) -> Vec<Binding> {
+    hotkeys::bindings(&app)
+}
+
+/// Bind an action to a shortcut such as `Ctrl+Shift+S`, or unbind it with
+/// none, and re-register all shortcuts.
+#[tauri::command]
+async fn set_hotkey(app: tauri::AppHandle, action: Action, accelerator: Option<String>) -> Result<Vec<Binding>, String> {
+    hotkeys::set(&app, action, accelerator)
+}
+
+#[tauri::command]
+async fn reset_hotkeys(app: tauri::AppHandle) -> Result<Vec<Binding>, String> {
+    hotkeys::reset(&app)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -150,11 +547,30 @@ pub fn run() {
     let streams = InterviewStreams(Arc::new(Mutex::new(None)));
 
     tauri::Builder::default()
-        .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_opener::init())
         .manage(streams)
+        .manage(LocalTranscriber::new())
+        .manage(LocalOcr::new())
+        .manage(AutoCapture::new())
+        .manage(ClipboardWatcher::new())
+        .manage(LlmClient::new())
+        .manage(LlmStreams::new())
+        .manage(ContextManager::new())
+        .manage(Hotkeys::new())
         .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(SessionStore::open(&data_dir)?);
+            app.manage(PromptStore::open(&data_dir)?);
+            app.manage(ResponseCache::open(&data_dir)?);
+            app.manage(Vault::open(&data_dir, &app.config().identifier)?);
+            vault::migrate_plaintext_key(app.handle());
+            llm::migrate_base_url(app.handle());
+            let redaction = redact::status(app.handle());
+            if redaction.enabled && !redaction.screenshot_masking {
+                eprintln!("[Redact] Screenshots won't be masked: no OCR engine available");
+            }
+
             let window = app.get_webview_window("main").unwrap();
             #[cfg(target_os = "windows")]
             {
@@ -171,42 +587,7 @@ pub fn run() {
                 }
             }
 
-            // Register Alt+1 global shortcut to toggle window visibility
-            #[cfg(desktop)]
-            {
-                use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
-                use tauri::Manager;
-
-                let toggle_shortcut = Shortcut::new(Some(Modifiers::ALT), Code::Digit1);
-
-                app.handle().plugin(
-                    tauri_plugin_global_shortcut::Builder::new()
-                        .with_handler(move |app, shortcut, event| {
-                            if shortcut == &toggle_shortcut && event.state() == ShortcutState::Pressed {
-                                if let Some(win) = app.get_webview_window("main") {
-                                    if win.is_minimized().unwrap_or(false) {
-                                        // Window is minimized — restore it
-                                        let _ = win.unminimize();
-                                        let _ = win.show();
-                                        let _ = win.set_focus();
-                                    } else if win.is_visible().unwrap_or(false) {
-                                        let _ = win.hide();
-                                    } else {
-                                        let _ = win.show();
-                                        let _ = win.set_focus();
-                                    }
-                                }
-                            }
-                        })
-                        .build(),
-                )?;
-
-                app.global_shortcut().register(
-                    Shortcut::new(Some(Modifiers::ALT), Code::Digit1)
-                )?;
-
-                println!("[Hotkey] Alt+1 registered: toggle window visibility");
-            }
+            hotkeys::init(app.handle())?;
 
             Ok(())
         })
@@ -217,7 +598,55 @@ pub fn run() {
             start_interview_mode,
             stop_interview_mode,
             get_audio_devices,
-            capture_screenshot
+            probe_audio_device,
+            list_sessions,
+            open_session,
+            rename_session,
+            delete_session,
+            export_session,
+            list_screenshots,
+            get_screenshot,
+            delete_screenshot,
+            export_screenshots,
+            preview_redaction,
+            get_redaction_status,
+            get_redaction_log,
+            search_sessions,
+            llm_generate,
+            llm_stream,
+            llm_cancel,
+            llm_list_models,
+            vault_status,
+            vault_unlock,
+            reset_vault,
+            set_credential,
+            delete_credential,
+            test_credential,
+            get_usage,
+            clear_llm_cache,
+            get_prices,
+            set_price,
+            get_context,
+            list_prompts,
+            get_prompt,
+            save_prompt,
+            reset_prompt,
+            delete_prompt,
+            preview_prompt,
+            start_local_transcription,
+            stop_local_transcription,
+            list_displays,
+            list_windows,
+            capture_screenshot,
+            start_auto_capture,
+            stop_auto_capture,
+            read_clipboard_text,
+            read_clipboard_image,
+            start_clipboard_watcher,
+            stop_clipboard_watcher,
+            get_hotkeys,
+            set_hotkey,
+            reset_hotkeys
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
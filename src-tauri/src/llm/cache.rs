@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use super::{LlmProvider, LlmRequest, LlmResponse, Part};
+use crate::sessions::now_ms;
+use crate::settings;
+
+const DEFAULT_TTL_HOURS: f64 = 24.0;
+const DEFAULT_MAX_MB: f64 = 50.0;
+
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    stored_at: i64,
+    response: LlmResponse,
+}
+
+struct Entry {
+    size: u64,
+    stored_at: i64,
+    used_at: i64,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<String, Entry>,
+    bytes: u64,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CacheStats {
+    pub enabled: bool,
+    /// Since the app started.
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub ttl_hours: f64,
+}
+
+/// Managed state: finished responses as one JSON file each under
+/// `<app data>/llm-cache`, named by the SHA-256 of what was asked.
+pub struct ResponseCache {
+    dir: PathBuf,
+    state: Mutex<State>,
+}
+
+struct Config {
+    enabled: bool,
+    ttl: Duration,
+    max_bytes: u64,
+}
+
+impl ResponseCache {
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        let dir = data_dir.join("llm-cache");
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+        let mut state = State::default();
+        let entries = std::fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let (Some(key), Ok(meta)) = (path.file_stem().and_then(|s| s.to_str()), entry.metadata()) else { continue };
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let stored_at = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_millis() as i64);
+            state.bytes += meta.len();
+            state.entries.insert(key.to_string(), Entry { size: meta.len(), stored_at, used_at: stored_at });
+        }
+        println!("[Cache] {} cached responses ({} KB)", state.entries.len(), state.bytes / 1024);
+        Ok(ResponseCache { dir, state: Mutex::new(state) })
+    }
+
+    fn get(&self, key: &str, ttl: Duration) -> Option<LlmResponse> {
+        let mut state = self.state.lock().unwrap();
+        let fresh = match state.entries.get(key) {
+            Some(entry) => now_ms() - entry.stored_at <= ttl.as_millis() as i64,
+            None => {
+                state.misses += 1;
+                return None;
+            }
+        };
+        let cached = fresh
+            .then(|| std::fs::read_to_string(self.path(key)).ok())
+            .flatten()
+            .and_then(|json| serde_json::from_str::<CachedResponse>(&json).ok());
+        match cached {
+            Some(cached) => {
+                state.hits += 1;
+                if let Some(entry) = state.entries.get_mut(key) {
+                    entry.used_at = now_ms();
+                }
+                Some(LlmResponse { cached: true, shared: false, ..cached.response })
+            }
+            // Expired or unreadable.
+            None => {
+                state.misses += 1;
+                self.remove(&mut state, key);
+                None
+            }
+        }
+    }
+
+    fn put(&self, key: &str, response: &LlmResponse, max_bytes: u64) {
+        let cached = CachedResponse { stored_at: now_ms(), response: response.clone() };
+        let Ok(json) = serde_json::to_string(&cached) else { return };
+        let size = json.len() as u64;
+        if size > max_bytes {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if let Err(e) = std::fs::write(self.path(key), &json) {
+            eprintln!("[Cache] ✗ Failed to store response: {}", e);
+            return;
+        }
+        if let Some(old) = state.entries.insert(
+            key.to_string(),
+            Entry { size, stored_at: cached.stored_at, used_at: cached.stored_at },
+        ) {
+            state.bytes -= old.size;
+        }
+        state.bytes += size;
+
+        // Least recently used first.
+        while state.bytes > max_bytes {
+            let Some(oldest) = state.entries.iter().min_by_key(|(_, e)| e.used_at).map(|(k, _)| k.clone()) else {
+                break;
+            };
+            self.remove(&mut state, &oldest);
+        }
+    }
+
+    fn remove(&self, state: &mut State, key: &str) {
+        if let Some(entry) = state.entries.remove(key) {
+            state.bytes -= entry.size;
+            let _ = std::fs::remove_file(self.path(key));
+        }
+    }
+
+    /// Delete every cached response. Hit and miss counts are kept.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        let keys: Vec<String> = state.entries.keys().cloned().collect();
+        for key in keys {
+            self.remove(&mut state, &key);
+        }
+        println!("[Cache] Cleared");
+    }
+
+    pub fn stats(&self, app: &AppHandle) -> CacheStats {
+        let config = config(app);
+        let state = self.state.lock().unwrap();
+        CacheStats {
+            enabled: config.enabled,
+            hits: state.hits,
+            misses: state.misses,
+            entries: state.entries.len(),
+            bytes: state.bytes,
+            max_bytes: config.max_bytes,
+            ttl_hours: config.ttl.as_secs_f64() / 3600.0,
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// `llm_cache_enabled` (default on), `llm_cache_ttl_hours` (24) and
+/// `llm_cache_max_mb` (50).
+fn config(app: &AppHandle) -> Config {
+    let hours = settings::get::<f64>(app, "llm_cache_ttl_hours").filter(|h| *h > 0.0).unwrap_or(DEFAULT_TTL_HOURS);
+    let mb = settings::get::<f64>(app, "llm_cache_max_mb").filter(|m| *m > 0.0).unwrap_or(DEFAULT_MAX_MB);
+    Config {
+        enabled: settings::get::<bool>(app, "llm_cache_enabled").unwrap_or(true),
+        ttl: Duration::from_secs_f64(hours * 3600.0),
+        max_bytes: (mb * 1024.0 * 1024.0) as u64,
+    }
+}
+
+/// A cached response to the same request, unless the cache is off or bypassed.
+pub fn lookup(app: &AppHandle, provider: &dyn LlmProvider, model: &str, request: &LlmRequest) -> Option<LlmResponse> {
+    let config = config(app);
+    if !config.enabled || request.bypass_cache {
+        return None;
+    }
+    let cache = app.try_state::<ResponseCache>()?;
+    cache.get(&key(provider.name(), provider.base_url(), model, request), config.ttl)
+}
+
+/// Cache a fresh response. Shared and cached responses are already stored.
+pub fn store(app: &AppHandle, provider: &dyn LlmProvider, model: &str, request: &LlmRequest, response: &LlmResponse) {
+    let config = config(app);
+    if !config.enabled || request.bypass_cache || response.shared || response.cached || response.text.trim().is_empty() {
+        return;
+    }
+    let Some(cache) = app.try_state::<ResponseCache>() else { return };
+    cache.put(&key(provider.name(), provider.base_url(), model, request), response, config.max_bytes);
+}
+
+/// SHA-256 over the provider and its base URL, model, generation options and
+/// the request input: text with whitespace runs collapsed, binary parts by
+/// their own hash. The URL keeps two OpenAI-compatible servers apart.
+fn key(provider: &str, base_url: &str, model: &str, request: &LlmRequest) -> String {
+    let mut hasher = Sha256::new();
+    let mut field = |value: &[u8]| {
+        hasher.update((value.len() as u64).to_le_bytes());
+        hasher.update(value);
+    };
+    field(provider.as_bytes());
+    field(base_url.as_bytes());
+    field(model.as_bytes());
+    field(normalize(request.system.as_deref().unwrap_or("")).as_bytes());
+    field(format!("{:?}/{:?}", request.temperature, request.max_output_tokens).as_bytes());
+    for part in &request.parts {
+        match part {
+            Part::Text { text } => {
+                field(b"text");
+                field(normalize(text).as_bytes());
+            }
+            Part::Image { mime_type, data } | Part::Audio { mime_type, data } => {
+                field(mime_type.as_bytes());
+                field(&Sha256::digest(data.as_bytes()));
+            }
+        }
+    }
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GEMINI: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+    fn request(texts: &[&str]) -> LlmRequest {
+        LlmRequest {
+            system: Some("You are a helpful assistant.".to_string()),
+            parts: texts.iter().map(|t| Part::Text { text: t.to_string() }).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn image(data: &str) -> Part {
+        Part::Image { mime_type: "image/png".to_string(), data: data.to_string() }
+    }
+
+    #[test]
+    fn whitespace_and_bookkeeping_fields_dont_change_the_key() {
+        let base = key("gemini", GEMINI, "gemini-2.0-flash", &request(&["What is  a\nB-tree?"]));
+        let mut other = request(&["  What is a B-tree? "]);
+        other.kind = Some("chat".to_string());
+        other.group = Some("hint".to_string());
+        assert_eq!(key("gemini", GEMINI, "gemini-2.0-flash", &other), base);
+    }
+
+    #[test]
+    fn provider_model_and_options_are_part_of_the_key() {
+        let req = request(&["What is a B-tree?"]);
+        let base = key("gemini", GEMINI, "gemini-2.0-flash", &req);
+        assert_ne!(key("openai", GEMINI, "gemini-2.0-flash", &req), base);
+        assert_ne!(key("gemini", GEMINI, "gemini-2.5-flash", &req), base);
+        let mut warmer = req.clone();
+        warmer.temperature = Some(0.9);
+        assert_ne!(key("gemini", GEMINI, "gemini-2.0-flash", &warmer), base);
+
+        // The same model name served by two OpenAI-compatible endpoints.
+        let local = key("openai", "http://localhost:8080/v1", "llama3", &req);
+        assert_ne!(key("openai", "https://api.openai.com/v1", "llama3", &req), local);
+        assert_eq!(key("openai", "http://localhost:8080/v1", "llama3", &req), local);
+    }
+
+    #[test]
+    fn parts_are_delimited_and_images_hashed() {
+        assert_ne!(key("p", "u", "m", &request(&["ab", "c"])), key("p", "u", "m", &request(&["a", "bc"])));
+
+        let mut first = request(&["Describe this"]);
+        first.parts.push(image("AAAA"));
+        let mut second = request(&["Describe this"]);
+        second.parts.push(image("AAAB"));
+        assert_ne!(key("p", "u", "m", &first), key("p", "u", "m", &second));
+        assert_eq!(key("p", "u", "m", &first), key("p", "u", "m", &first.clone()));
+    }
+}
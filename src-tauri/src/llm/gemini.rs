@@ -0,0 +1,248 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::{error_for_status, for_each_line, sse_data, LlmError, LlmProvider, LlmRequest, LlmResponse, ModelInfo, Part, Usage};
+
+const BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const DEFAULT_MODEL: &str = "models/gemini-1.5-flash";
+
+/// Google Gemini via the Generative Language REST API.
+pub struct GeminiProvider {
+    http: reqwest::Client,
+    api_key: String,
+}
+
+impl GeminiProvider {
+    pub fn new(http: reqwest::Client, api_key: String) -> Self {
+        GeminiProvider { http, api_key }
+    }
+
+    /// Model names are `models/<id>`; accept bare IDs too.
+    fn model_path(model: &str) -> String {
+        if model.starts_with("models/") {
+            model.to_string()
+        } else {
+            format!("models/{}", model)
+        }
+    }
+
+    fn request_body(request: &LlmRequest) -> Value {
+        let parts: Vec<Value> = request
+            .parts
+            .iter()
+            .map(|p| match p {
+                Part::Text { text } => json!({ "text": text }),
+                Part::Image { mime_type, data } | Part::Audio { mime_type, data } => {
+                    json!({ "inlineData": { "mimeType": mime_type, "data": data } })
+                }
+            })
+            .collect();
+
+        let mut body = json!({ "contents": [{ "role": "user", "parts": parts }] });
+        if let Some(system) = &request.system {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        let mut config = serde_json::Map::new();
+        if let Some(t) = request.temperature {
+            config.insert("temperature".into(), json!(t));
+        }
+        if let Some(n) = request.max_output_tokens {
+            config.insert("maxOutputTokens".into(), json!(n));
+        }
+        if !config.is_empty() {
+            body["generationConfig"] = Value::Object(config);
+        }
+        body
+    }
+
+    /// Concatenated text of the first candidate.
+    fn candidate_text(response: &Value) -> String {
+        response["candidates"][0]["content"]["parts"]
+            .as_array()
+            .map(|parts| parts.iter().filter_map(|p| p["text"].as_str()).collect::<String>())
+            .unwrap_or_default()
+    }
+
+    fn usage(response: &Value) -> Usage {
+        let meta = &response["usageMetadata"];
+        Usage {
+            input_tokens: meta["promptTokenCount"].as_u64().unwrap_or(0),
+            output_tokens: meta["candidatesTokenCount"].as_u64().unwrap_or(0),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn base_url(&self) -> &str {
+        BASE_URL
+    }
+
+    fn default_model(&self) -> Option<&'static str> {
+        Some(DEFAULT_MODEL)
+    }
+
+    async fn generate(&self, request: &LlmRequest, model: &str) -> Result<LlmResponse, LlmError> {
+        let model = Self::model_path(model);
+        let response = self
+            .http
+            .post(format!("{}/{}:generateContent", BASE_URL, model))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&Self::request_body(request))
+            .send()
+            .await?;
+        let body: Value = error_for_status(response).await?.json().await?;
+
+        if body["candidates"].as_array().is_none_or(|c| c.is_empty()) {
+            let reason = body["promptFeedback"]["blockReason"].as_str().unwrap_or("no candidates returned");
+            return Err(LlmError::InvalidResponse(reason.to_string()));
+        }
+
+        Ok(LlmResponse {
+            provider: self.name().to_string(),
+            model,
+            text: Self::candidate_text(&body),
+            usage: Self::usage(&body),
+            shared: false,
+            cached: false,
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: &LlmRequest,
+        model: &str,
+        on_delta: &mut (dyn for<'d> FnMut(&'d str) + Send),
+    ) -> Result<LlmResponse, LlmError> {
+        let model = Self::model_path(model);
+        let response = self
+            .http
+            .post(format!("{}/{}:streamGenerateContent?alt=sse", BASE_URL, model))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&Self::request_body(request))
+            .send()
+            .await?;
+        let response = error_for_status(response).await?;
+
+        let mut text = String::new();
+        let mut usage = Usage::default();
+        let mut block_reason = None;
+        for_each_line(response, |line| {
+            let Some(data) = sse_data(line) else { return Ok(()) };
+            let chunk: Value = serde_json::from_str(data).map_err(|e| LlmError::InvalidResponse(e.to_string()))?;
+            let delta = Self::candidate_text(&chunk);
+            if !delta.is_empty() {
+                on_delta(&delta);
+                text.push_str(&delta);
+            }
+            // Every chunk carries cumulative usage; the last one wins.
+            if chunk["usageMetadata"].is_object() {
+                usage = Self::usage(&chunk);
+            }
+            if let Some(reason) = chunk["promptFeedback"]["blockReason"].as_str() {
+                block_reason = Some(reason.to_string());
+            }
+            Ok(())
+        })
+        .await?;
+
+        if let (true, Some(reason)) = (text.is_empty(), block_reason) {
+            return Err(LlmError::InvalidResponse(reason));
+        }
+        Ok(LlmResponse {
+            provider: self.name().to_string(),
+            model,
+            text,
+            usage,
+            shared: false,
+            cached: false,
+        })
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        let response = self
+            .http
+            .get(format!("{}/models?pageSize=1000", BASE_URL))
+            .header("x-goog-api-key", &self.api_key)
+            .send()
+            .await?;
+        let body: Value = error_for_status(response).await?.json().await?;
+
+        let models = body["models"]
+            .as_array()
+            .ok_or_else(|| LlmError::InvalidResponse("missing `models`".to_string()))?;
+        Ok(models
+            .iter()
+            .filter(|m| {
+                m["supportedGenerationMethods"]
+                    .as_array()
+                    .is_some_and(|methods| methods.iter().any(|x| x == "generateContent"))
+            })
+            .filter_map(|m| {
+                let id = m["name"].as_str()?.to_string();
+                let display_name = m["displayName"].as_str().unwrap_or(&id).to_string();
+                Some(ModelInfo { id, display_name })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(parts: Vec<Part>) -> LlmRequest {
+        LlmRequest { system: Some("Be brief.".to_string()), parts, ..Default::default() }
+    }
+
+    #[test]
+    fn request_body_has_system_instruction_inline_data_and_config() {
+        let mut req = request(vec![
+            Part::Text { text: "What is this?".to_string() },
+            Part::Image { mime_type: "image/png".to_string(), data: "AAAA".to_string() },
+        ]);
+        req.temperature = Some(0.5);
+        req.max_output_tokens = Some(100);
+        let body = GeminiProvider::request_body(&req);
+
+        assert_eq!(body["systemInstruction"], json!({ "parts": [{ "text": "Be brief." }] }));
+        assert_eq!(
+            body["contents"],
+            json!([{ "role": "user", "parts": [
+                { "text": "What is this?" },
+                { "inlineData": { "mimeType": "image/png", "data": "AAAA" } }
+            ] }])
+        );
+        assert_eq!(body["generationConfig"], json!({ "temperature": 0.5, "maxOutputTokens": 100 }));
+    }
+
+    #[test]
+    fn request_body_omits_unset_fields() {
+        let mut req = request(vec![Part::Text { text: "Hi".to_string() }]);
+        req.system = None;
+        let body = GeminiProvider::request_body(&req);
+        assert!(body.get("systemInstruction").is_none());
+        assert!(body.get("generationConfig").is_none());
+    }
+
+    #[test]
+    fn text_and_usage_from_response_and_stream_chunks() {
+        let response = json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "A " }, { "text": "tree." }] } }],
+            "usageMetadata": { "promptTokenCount": 12, "candidatesTokenCount": 3 }
+        });
+        assert_eq!(GeminiProvider::candidate_text(&response), "A tree.");
+        let usage = GeminiProvider::usage(&response);
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 3));
+
+        let chunk = json!({ "candidates": [{ "content": { "parts": [{ "text": "A " }] } }] });
+        assert_eq!(GeminiProvider::candidate_text(&chunk), "A ");
+        assert_eq!(GeminiProvider::usage(&chunk).input_tokens, 0);
+        let blocked = json!({ "promptFeedback": { "blockReason": "SAFETY" } });
+        assert_eq!(GeminiProvider::candidate_text(&blocked), "");
+    }
+}
@@ -0,0 +1,436 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::redact;
+use crate::sessions::SessionStore;
+use crate::settings;
+use crate::vault::Vault;
+use scheduler::{Limits, Scheduler};
+
+pub mod cache;
+pub mod gemini;
+pub mod ollama;
+pub mod openai;
+pub mod scheduler;
+pub mod stream;
+pub mod usage;
+
+/// One piece of request input. Binary inputs are base64-encoded.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Part {
+    Text { text: String },
+    Image { mime_type: String, data: String },
+    Audio { mime_type: String, data: String },
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct LlmRequest {
+    /// Model ID; defaults to the model selected in Settings.
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub system: Option<String>,
+    /// Prompt template rendered into a text part in front of `parts`.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Template variables; fill in or override the context values.
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    #[serde(default)]
+    pub parts: Vec<Part>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+    /// When set (`chat`, `hint`, `screenshot`, ...), the exchange is recorded
+    /// in the active session under this kind.
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// Stale group, e.g. `hint`: a newer request in the same group drops
+    /// this one if it is still waiting in the queue.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Skip the response cache for this request, both lookup and store.
+    #[serde(default)]
+    pub bypass_cache: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct LlmResponse {
+    pub provider: String,
+    pub model: String,
+    pub text: String,
+    pub usage: Usage,
+    /// Shared from an identical request that was already pending.
+    pub shared: bool,
+    /// Served from the response cache; `usage` is that of the original request.
+    #[serde(default)]
+    pub cached: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ModelInfo {
+    pub id: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum LlmError {
+    /// Missing API key, unknown provider and similar setup problems.
+    Config(String),
+    /// The provider answered with a non-success status.
+    Http { status: u16, body: String, retry_after: Option<Duration> },
+    /// The request never got a response (DNS, TLS, timeout, ...).
+    Network(String),
+    /// The response did not have the shape we expected.
+    InvalidResponse(String),
+    /// Dropped by the scheduler before it was sent.
+    Cancelled(String),
+    /// The hard spending budget is used up.
+    Budget(String),
+}
+
+impl LlmError {
+    /// Rate limits, server errors and network failures are worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LlmError::Http { status, .. } => matches!(status, 408 | 429 | 500 | 502 | 503 | 504),
+            LlmError::Network(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for LlmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LlmError::Config(msg) => write!(f, "{}", msg),
+            LlmError::Http { status, body, .. } => write!(f, "API Error {}: {}", status, body),
+            LlmError::Network(msg) => write!(f, "Network error: {}", msg),
+            LlmError::InvalidResponse(msg) => write!(f, "Invalid response: {}", msg),
+            LlmError::Cancelled(msg) => write!(f, "Cancelled: {}", msg),
+            LlmError::Budget(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LlmError {}
+
+impl From<reqwest::Error> for LlmError {
+    fn from(e: reqwest::Error) -> Self {
+        LlmError::Network(e.to_string())
+    }
+}
+
+/// A model backend. The frontend only talks to the `llm_*` commands, so API
+/// keys and provider details stay on the Rust side.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Base URL requests are sent to.
+    fn base_url(&self) -> &str;
+
+    /// Model used when neither the request nor Settings names one.
+    fn default_model(&self) -> Option<&'static str> {
+        None
+    }
+
+    async fn generate(&self, request: &LlmRequest, model: &str) -> Result<LlmResponse, LlmError>;
+
+    /// Like `generate`, but through the provider's streaming endpoint; each
+    /// text delta is passed to `on_delta` as it arrives.
+    async fn stream(
+        &self,
+        request: &LlmRequest,
+        model: &str,
+        on_delta: &mut (dyn for<'d> FnMut(&'d str) + Send),
+    ) -> Result<LlmResponse, LlmError>;
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError>;
+}
+
+/// Managed state: the shared HTTP client the providers are built on, and
+/// the scheduler every request goes through.
+pub struct LlmClient {
+    http: reqwest::Client,
+    scheduler: Scheduler,
+}
+
+impl LlmClient {
+    pub fn new() -> Self {
+        let http = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(180))
+            .build()
+            .expect("failed to build HTTP client");
+        LlmClient { http, scheduler: Scheduler::new() }
+    }
+
+    /// Build the provider configured in Settings (`llm_provider`, default
+    /// Gemini).
+    pub fn provider(&self, app: &AppHandle) -> Result<Box<dyn LlmProvider>, LlmError> {
+        let provider: String = settings::get(app, "llm_provider").unwrap_or_else(|| "gemini".to_string());
+        self.build_provider(app, &provider)
+    }
+
+    /// Build provider `name` with its `<name>_base_url` and its credential
+    /// from the vault.
+    pub fn build_provider(&self, app: &AppHandle, name: &str) -> Result<Box<dyn LlmProvider>, LlmError> {
+        let base_url = non_empty(settings::get(app, &format!("{}_base_url", name)));
+        let api_key = match name {
+            "ollama" => None,
+            _ => non_empty(app.state::<Vault>().get(name).map_err(LlmError::Config)?),
+        };
+        let http = self.http.clone();
+
+        match name {
+            "gemini" => {
+                let api_key = api_key.ok_or_else(|| LlmError::Config("No API key configured".to_string()))?;
+                Ok(Box::new(gemini::GeminiProvider::new(http, api_key)))
+            }
+            "openai" => Ok(Box::new(openai::OpenAiProvider::new(http, base_url, api_key))),
+            "ollama" => Ok(Box::new(ollama::OllamaProvider::new(http, base_url))),
+            other => Err(LlmError::Config(format!("Unknown LLM provider: {}", other))),
+        }
+    }
+
+    pub async fn generate(&self, app: &AppHandle, request: &LlmRequest) -> Result<LlmResponse, LlmError> {
+        let request = &*redact::request(app, request);
+        let (provider, model) = self.resolve(app, request)?;
+        if let Some(hit) = cache::lookup(app, provider.as_ref(), &model, request) {
+            println!("[LLM] ✓ Cache hit for {} request", model);
+            return Ok(hit);
+        }
+        usage::check_budget(app)?;
+        let limits = limits(app, provider.name());
+        println!("[LLM] {} request to {} ({} parts)", provider.name(), model, request.parts.len());
+
+        let key = scheduler::request_key(provider.name(), &model, request);
+        let (response, shared) = self
+            .scheduler
+            .dedup(key, async {
+                let mut ticket = self.scheduler.ticket(request.group.as_deref());
+                let mut attempt = 0;
+                loop {
+                    let permit = self.scheduler.acquire(provider.name(), limits, &mut ticket).await?;
+                    let error = match provider.generate(request, &model).await {
+                        Ok(response) => return Ok(response),
+                        Err(e) => e,
+                    };
+                    drop(permit);
+                    self.scheduler.backoff(attempt, error, &mut ticket).await?;
+                    attempt += 1;
+                }
+            })
+            .await;
+        log_result(&response);
+        let response = response.map(|r| LlmResponse { shared, ..r })?;
+        usage::record(app, request, &response);
+        cache::store(app, provider.as_ref(), &model, request, &response);
+        Ok(response)
+    }
+
+    pub async fn stream(
+        &self,
+        app: &AppHandle,
+        request: &LlmRequest,
+        on_delta: &mut (dyn for<'d> FnMut(&'d str) + Send),
+    ) -> Result<LlmResponse, LlmError> {
+        let request = &*redact::request(app, request);
+        let (provider, model) = self.resolve(app, request)?;
+        if let Some(hit) = cache::lookup(app, provider.as_ref(), &model, request) {
+            println!("[LLM] ✓ Cache hit for {} stream", model);
+            on_delta(&hit.text);
+            return Ok(hit);
+        }
+        usage::check_budget(app)?;
+        let limits = limits(app, provider.name());
+        println!("[LLM] {} stream to {} ({} parts)", provider.name(), model, request.parts.len());
+
+        let key = scheduler::request_key(provider.name(), &model, request);
+        let (response, shared) = self
+            .scheduler
+            .dedup(key, async {
+                let mut ticket = self.scheduler.ticket(request.group.as_deref());
+                let mut attempt = 0;
+                loop {
+                    let permit = self.scheduler.acquire(provider.name(), limits, &mut ticket).await?;
+                    let mut started = false;
+                    let result = provider
+                        .stream(request, &model, &mut |delta| {
+                            started = true;
+                            on_delta(delta);
+                        })
+                        .await;
+                    let error = match result {
+                        Ok(response) => return Ok(response),
+                        // Text already reached the frontend; a retry would repeat it.
+                        Err(e) if started => return Err(e),
+                        Err(e) => e,
+                    };
+                    drop(permit);
+                    self.scheduler.backoff(attempt, error, &mut ticket).await?;
+                    attempt += 1;
+                }
+            })
+            .await;
+        log_result(&response);
+
+        // A joined request saw no deltas of its own; relay the text in one piece.
+        let response = response.map(|r| LlmResponse { shared, ..r })?;
+        usage::record(app, request, &response);
+        cache::store(app, provider.as_ref(), &model, request, &response);
+        if shared {
+            on_delta(&response.text);
+        }
+        Ok(response)
+    }
+
+    pub async fn list_models(&self, app: &AppHandle) -> Result<Vec<ModelInfo>, LlmError> {
+        self.provider(app)?.list_models().await
+    }
+
+    /// Provider plus the model to use: request model → Settings → provider default.
+    fn resolve(&self, app: &AppHandle, request: &LlmRequest) -> Result<(Box<dyn LlmProvider>, String), LlmError> {
+        let provider = self.provider(app)?;
+        let model = request
+            .model
+            .clone()
+            .or_else(|| non_empty(settings::get(app, "selected_model")))
+            .or_else(|| provider.default_model().map(str::to_string))
+            .ok_or_else(|| LlmError::Config("No model selected".to_string()))?;
+        Ok((provider, model))
+    }
+}
+
+/// Provider defaults, overridden by the `llm_max_concurrent` and
+/// `llm_requests_per_minute` settings (0 disables rate limiting).
+fn limits(app: &AppHandle, provider: &str) -> Limits {
+    let mut limits = Limits::default_for(provider);
+    if let Some(n) = settings::get::<usize>(app, "llm_max_concurrent") {
+        limits.max_concurrent = n.max(1);
+    }
+    if let Some(rpm) = settings::get::<f64>(app, "llm_requests_per_minute") {
+        limits.requests_per_minute = (rpm > 0.0).then_some(rpm);
+    }
+    limits
+}
+
+fn log_result(response: &Result<LlmResponse, LlmError>) {
+    match response {
+            Ok(r) => println!(
+                "[LLM] ✓ {} tokens in, {} tokens out",
+                r.usage.input_tokens, r.usage.output_tokens
+            ),
+            Err(LlmError::Http { status, retry_after: Some(wait), .. }) => {
+                eprintln!("[LLM] ✗ HTTP {} (retry after {:?})", status, wait)
+            }
+            Err(e) => eprintln!("[LLM] ✗ {}", e),
+    }
+}
+
+/// Move the shared `llm_base_url` of older versions to the base URL setting
+/// of the provider it was used with.
+pub fn migrate_base_url(app: &AppHandle) {
+    let Some(base_url) = non_empty(settings::get(app, "llm_base_url")) else { return };
+    let provider: String = settings::get(app, "llm_provider").unwrap_or_else(|| "gemini".to_string());
+    let key = format!("{}_base_url", provider);
+    let result = match settings::get::<String>(app, &key) {
+        Some(_) => Ok(()),
+        None => settings::set(app, &key, &base_url),
+    };
+    match result.and_then(|()| settings::remove(app, "llm_base_url")) {
+        Ok(()) => println!("[LLM] ✓ Moved llm_base_url to {}", key),
+        Err(e) => eprintln!("[LLM] ✗ Failed to migrate llm_base_url: {}", e),
+    }
+}
+
+/// Store a finished exchange in the active session when the request has a `kind`.
+pub fn record_exchange(app: &AppHandle, request: &LlmRequest, response: &LlmResponse) {
+    let Some(kind) = &request.kind else { return };
+    if response.shared {
+        return;
+    }
+    let Some(sessions) = app.try_state::<SessionStore>() else { return };
+    let prompt = describe_parts(&request.parts);
+    if let Err(e) = sessions.add_exchange(kind, Some(&response.model), &prompt, &response.text) {
+        eprintln!("[Sessions] ✗ {}", e);
+    }
+}
+
+/// Text-only rendering of a request's input, for session history.
+pub fn describe_parts(parts: &[Part]) -> String {
+    parts
+        .iter()
+        .map(|p| match p {
+            Part::Text { text } => text.clone(),
+            Part::Image { .. } => "[image]".to_string(),
+            Part::Audio { .. } => "[audio]".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Parse a `Retry-After` header given in seconds.
+pub(crate) fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|s| *s >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// Feed each line of a streaming response body to `f` as it arrives.
+pub(crate) async fn for_each_line(
+    mut response: reqwest::Response,
+    mut f: impl FnMut(&str) -> Result<(), LlmError>,
+) -> Result<(), LlmError> {
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            f(String::from_utf8_lossy(&line).trim_end())?;
+        }
+    }
+    if !buffer.is_empty() {
+        f(String::from_utf8_lossy(&buffer).trim_end())?;
+    }
+    Ok(())
+}
+
+/// Payload of an SSE `data:` line; `None` for comments, other fields and `[DONE]`.
+pub(crate) fn sse_data(line: &str) -> Option<&str> {
+    let data = line.strip_prefix("data:")?.trim();
+    (!data.is_empty() && data != "[DONE]").then_some(data)
+}
+
+/// Turn a non-success response into `LlmError::Http`.
+pub(crate) async fn error_for_status(response: reqwest::Response) -> Result<reqwest::Response, LlmError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let retry_after = retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    Err(LlmError::Http { status: status.as_u16(), body, retry_after })
+}
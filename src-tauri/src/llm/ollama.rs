@@ -0,0 +1,212 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::{error_for_status, for_each_line, LlmError, LlmProvider, LlmRequest, LlmResponse, ModelInfo, Part, Usage};
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// A local Ollama server via its native `/api/chat` endpoint. Images are
+/// passed to vision models (llava, llama3.2-vision, ...); audio is rejected.
+pub struct OllamaProvider {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl OllamaProvider {
+    pub fn new(http: reqwest::Client, base_url: Option<String>) -> Self {
+        let base_url = base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        OllamaProvider { http, base_url: base_url.trim_end_matches('/').to_string() }
+    }
+
+    fn request_body(request: &LlmRequest, model: &str, stream: bool) -> Result<Value, LlmError> {
+        let mut text = Vec::new();
+        let mut images = Vec::new();
+        for part in &request.parts {
+            match part {
+                Part::Text { text: t } => text.push(t.as_str()),
+                Part::Image { data, .. } => images.push(data.as_str()),
+                Part::Audio { .. } => {
+                    return Err(LlmError::Config("Ollama models do not accept audio input".to_string()))
+                }
+            }
+        }
+
+        let mut user = json!({ "role": "user", "content": text.join("\n\n") });
+        if !images.is_empty() {
+            user["images"] = json!(images);
+        }
+        let mut messages = Vec::new();
+        if let Some(system) = &request.system {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        messages.push(user);
+
+        let mut options = serde_json::Map::new();
+        if let Some(t) = request.temperature {
+            options.insert("temperature".into(), json!(t));
+        }
+        if let Some(n) = request.max_output_tokens {
+            options.insert("num_predict".into(), json!(n));
+        }
+        Ok(json!({ "model": model, "messages": messages, "stream": stream, "options": options }))
+    }
+
+    /// Text of a response, or of a stream chunk.
+    fn message_text(response: &Value) -> Option<&str> {
+        response["message"]["content"].as_str()
+    }
+
+    fn usage(response: &Value) -> Usage {
+        Usage {
+            input_tokens: response["prompt_eval_count"].as_u64().unwrap_or(0),
+            output_tokens: response["eval_count"].as_u64().unwrap_or(0),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn generate(&self, request: &LlmRequest, model: &str) -> Result<LlmResponse, LlmError> {
+        let body = Self::request_body(request, model, false)?;
+        let response = self.http.post(format!("{}/api/chat", self.base_url)).json(&body).send().await?;
+        let body: Value = error_for_status(response).await?.json().await?;
+
+        let text = Self::message_text(&body)
+            .ok_or_else(|| LlmError::InvalidResponse("missing `message.content`".to_string()))?;
+        Ok(LlmResponse {
+            provider: self.name().to_string(),
+            model: model.to_string(),
+            text: text.to_string(),
+            usage: Self::usage(&body),
+            shared: false,
+            cached: false,
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: &LlmRequest,
+        model: &str,
+        on_delta: &mut (dyn for<'d> FnMut(&'d str) + Send),
+    ) -> Result<LlmResponse, LlmError> {
+        let body = Self::request_body(request, model, true)?;
+        let response = self.http.post(format!("{}/api/chat", self.base_url)).json(&body).send().await?;
+        let response = error_for_status(response).await?;
+
+        // Newline-delimited JSON; the final object has `done: true` and the token counts.
+        let mut text = String::new();
+        let mut usage = Usage::default();
+        for_each_line(response, |line| {
+            if line.trim().is_empty() {
+                return Ok(());
+            }
+            let chunk: Value = serde_json::from_str(line).map_err(|e| LlmError::InvalidResponse(e.to_string()))?;
+            if let Some(error) = chunk["error"].as_str() {
+                return Err(LlmError::InvalidResponse(error.to_string()));
+            }
+            if let Some(delta) = Self::message_text(&chunk) {
+                if !delta.is_empty() {
+                    on_delta(delta);
+                    text.push_str(delta);
+                }
+            }
+            if chunk["done"].as_bool() == Some(true) {
+                usage = Self::usage(&chunk);
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(LlmResponse {
+            provider: self.name().to_string(),
+            model: model.to_string(),
+            text,
+            usage,
+            shared: false,
+            cached: false,
+        })
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        let response = self.http.get(format!("{}/api/tags", self.base_url)).send().await?;
+        let body: Value = error_for_status(response).await?.json().await?;
+
+        let models = body["models"]
+            .as_array()
+            .ok_or_else(|| LlmError::InvalidResponse("missing `models`".to_string()))?;
+        Ok(models
+            .iter()
+            .filter_map(|m| {
+                let id = m["name"].as_str()?.to_string();
+                Some(ModelInfo { display_name: id.clone(), id })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(parts: Vec<Part>) -> LlmRequest {
+        LlmRequest { system: Some("Be brief.".to_string()), parts, ..Default::default() }
+    }
+
+    #[test]
+    fn request_body_joins_text_and_lists_images_and_options() {
+        let mut req = request(vec![
+            Part::Text { text: "What is this?".to_string() },
+            Part::Image { mime_type: "image/png".to_string(), data: "AAAA".to_string() },
+            Part::Text { text: "Be specific.".to_string() },
+        ]);
+        req.temperature = Some(0.5);
+        req.max_output_tokens = Some(100);
+        let body = OllamaProvider::request_body(&req, "llava", true).unwrap();
+
+        assert_eq!(body["model"], "llava");
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["messages"][0], json!({ "role": "system", "content": "Be brief." }));
+        assert_eq!(
+            body["messages"][1],
+            json!({ "role": "user", "content": "What is this?\n\nBe specific.", "images": ["AAAA"] })
+        );
+        assert_eq!(body["options"], json!({ "temperature": 0.5, "num_predict": 100 }));
+    }
+
+    #[test]
+    fn request_body_omits_unset_fields_and_rejects_audio() {
+        let mut req = request(vec![Part::Text { text: "Hi".to_string() }]);
+        req.system = None;
+        let body = OllamaProvider::request_body(&req, "llama3", false).unwrap();
+        assert_eq!(body["messages"], json!([{ "role": "user", "content": "Hi" }]));
+        assert_eq!(body["options"], json!({}));
+
+        let audio = request(vec![Part::Audio { mime_type: "audio/wav".to_string(), data: "AAAA".to_string() }]);
+        assert!(matches!(OllamaProvider::request_body(&audio, "m", false), Err(LlmError::Config(_))));
+    }
+
+    #[test]
+    fn text_and_usage_from_response_and_stream_chunks() {
+        let response = json!({
+            "message": { "role": "assistant", "content": "A tree." },
+            "done": true,
+            "prompt_eval_count": 12,
+            "eval_count": 3
+        });
+        assert_eq!(OllamaProvider::message_text(&response), Some("A tree."));
+        let usage = OllamaProvider::usage(&response);
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 3));
+
+        let chunk = json!({ "message": { "role": "assistant", "content": "A " }, "done": false });
+        assert_eq!(OllamaProvider::message_text(&chunk), Some("A "));
+        assert_eq!(OllamaProvider::usage(&chunk).output_tokens, 0);
+    }
+}
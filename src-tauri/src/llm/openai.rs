@@ -0,0 +1,240 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::{error_for_status, for_each_line, sse_data, LlmError, LlmProvider, LlmRequest, LlmResponse, ModelInfo, Part, Usage};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Any server speaking the OpenAI Chat Completions API (OpenAI, OpenRouter,
+/// LM Studio, vLLM, llama.cpp server, ...).
+pub struct OpenAiProvider {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiProvider {
+    pub fn new(http: reqwest::Client, base_url: Option<String>, api_key: Option<String>) -> Self {
+        let base_url = base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        OpenAiProvider { http, base_url: base_url.trim_end_matches('/').to_string(), api_key }
+    }
+
+    fn authorized(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    fn request_body(request: &LlmRequest, model: &str) -> Result<Value, LlmError> {
+        let mut content = Vec::new();
+        for part in &request.parts {
+            content.push(match part {
+                Part::Text { text } => json!({ "type": "text", "text": text }),
+                Part::Image { mime_type, data } => json!({
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:{};base64,{}", mime_type, data) }
+                }),
+                Part::Audio { mime_type, data } => {
+                    let format = match mime_type.as_str() {
+                        "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+                        "audio/mpeg" | "audio/mp3" => "mp3",
+                        other => {
+                            return Err(LlmError::Config(format!("Unsupported audio type for this provider: {}", other)))
+                        }
+                    };
+                    json!({ "type": "input_audio", "input_audio": { "data": data, "format": format } })
+                }
+            });
+        }
+
+        let mut messages = Vec::new();
+        if let Some(system) = &request.system {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        messages.push(json!({ "role": "user", "content": content }));
+
+        let mut body = json!({ "model": model, "messages": messages });
+        if let Some(t) = request.temperature {
+            body["temperature"] = json!(t);
+        }
+        if let Some(n) = request.max_output_tokens {
+            body["max_tokens"] = json!(n);
+        }
+        Ok(body)
+    }
+
+    fn message_text(response: &Value) -> Option<&str> {
+        response["choices"][0]["message"]["content"].as_str()
+    }
+
+    fn delta_text(chunk: &Value) -> Option<&str> {
+        chunk["choices"][0]["delta"]["content"].as_str()
+    }
+
+    fn usage(response: &Value) -> Usage {
+        let usage = &response["usage"];
+        Usage {
+            input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+            output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn generate(&self, request: &LlmRequest, model: &str) -> Result<LlmResponse, LlmError> {
+        let body = Self::request_body(request, model)?;
+        let response = self
+            .authorized(self.http.post(format!("{}/chat/completions", self.base_url)))
+            .json(&body)
+            .send()
+            .await?;
+        let body: Value = error_for_status(response).await?.json().await?;
+
+        let text = Self::message_text(&body)
+            .ok_or_else(|| LlmError::InvalidResponse("missing `choices[0].message.content`".to_string()))?;
+        Ok(LlmResponse {
+            provider: self.name().to_string(),
+            model: body["model"].as_str().unwrap_or(model).to_string(),
+            text: text.to_string(),
+            usage: Self::usage(&body),
+            shared: false,
+            cached: false,
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: &LlmRequest,
+        model: &str,
+        on_delta: &mut (dyn for<'d> FnMut(&'d str) + Send),
+    ) -> Result<LlmResponse, LlmError> {
+        let mut body = Self::request_body(request, model)?;
+        body["stream"] = json!(true);
+        body["stream_options"] = json!({ "include_usage": true });
+        let response = self
+            .authorized(self.http.post(format!("{}/chat/completions", self.base_url)))
+            .json(&body)
+            .send()
+            .await?;
+        let response = error_for_status(response).await?;
+
+        let mut text = String::new();
+        let mut usage = Usage::default();
+        let mut served_model = None;
+        for_each_line(response, |line| {
+            let Some(data) = sse_data(line) else { return Ok(()) };
+            let chunk: Value = serde_json::from_str(data).map_err(|e| LlmError::InvalidResponse(e.to_string()))?;
+            if let Some(delta) = Self::delta_text(&chunk) {
+                if !delta.is_empty() {
+                    on_delta(delta);
+                    text.push_str(delta);
+                }
+            }
+            // Only the final chunk has usage, and only on servers that honour `include_usage`.
+            if chunk["usage"].is_object() {
+                usage = Self::usage(&chunk);
+            }
+            if served_model.is_none() {
+                served_model = chunk["model"].as_str().map(str::to_string);
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(LlmResponse {
+            provider: self.name().to_string(),
+            model: served_model.unwrap_or_else(|| model.to_string()),
+            text,
+            usage,
+            shared: false,
+            cached: false,
+        })
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        let response = self.authorized(self.http.get(format!("{}/models", self.base_url))).send().await?;
+        let body: Value = error_for_status(response).await?.json().await?;
+
+        let models = body["data"]
+            .as_array()
+            .ok_or_else(|| LlmError::InvalidResponse("missing `data`".to_string()))?;
+        Ok(models
+            .iter()
+            .filter_map(|m| {
+                let id = m["id"].as_str()?.to_string();
+                Some(ModelInfo { display_name: id.clone(), id })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(parts: Vec<Part>) -> LlmRequest {
+        LlmRequest { system: Some("Be brief.".to_string()), parts, ..Default::default() }
+    }
+
+    #[test]
+    fn request_body_has_system_message_image_url_and_options() {
+        let mut req = request(vec![
+            Part::Text { text: "What is this?".to_string() },
+            Part::Image { mime_type: "image/png".to_string(), data: "AAAA".to_string() },
+        ]);
+        req.temperature = Some(0.5);
+        req.max_output_tokens = Some(100);
+        let body = OpenAiProvider::request_body(&req, "gpt-4o").unwrap();
+
+        assert_eq!(body["model"], "gpt-4o");
+        assert_eq!(body["messages"][0], json!({ "role": "system", "content": "Be brief." }));
+        assert_eq!(body["messages"][1]["role"], "user");
+        assert_eq!(body["messages"][1]["content"][0], json!({ "type": "text", "text": "What is this?" }));
+        assert_eq!(body["messages"][1]["content"][1]["image_url"]["url"], "data:image/png;base64,AAAA");
+        assert_eq!(body["temperature"], 0.5);
+        assert_eq!(body["max_tokens"], 100);
+    }
+
+    #[test]
+    fn request_body_omits_unset_fields_and_rejects_unknown_audio() {
+        let mut req = request(vec![Part::Audio { mime_type: "audio/wav".to_string(), data: "AAAA".to_string() }]);
+        req.system = None;
+        let body = OpenAiProvider::request_body(&req, "m").unwrap();
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["content"][0]["input_audio"]["format"], "wav");
+        assert!(body.get("temperature").is_none() && body.get("max_tokens").is_none());
+
+        let ogg = request(vec![Part::Audio { mime_type: "audio/ogg".to_string(), data: "AAAA".to_string() }]);
+        assert!(matches!(OpenAiProvider::request_body(&ogg, "m"), Err(LlmError::Config(_))));
+    }
+
+    #[test]
+    fn text_and_usage_from_response_and_stream_chunks() {
+        let response = json!({
+            "model": "gpt-4o-2024-08-06",
+            "choices": [{ "message": { "role": "assistant", "content": "A tree." } }],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 3 }
+        });
+        assert_eq!(OpenAiProvider::message_text(&response), Some("A tree."));
+        let usage = OpenAiProvider::usage(&response);
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 3));
+
+        let chunk = json!({ "choices": [{ "delta": { "content": "A " } }] });
+        assert_eq!(OpenAiProvider::delta_text(&chunk), Some("A "));
+        // With `include_usage`, the last chunk has no choices, only usage.
+        let last = json!({ "choices": [], "usage": { "prompt_tokens": 12, "completion_tokens": 3 } });
+        assert_eq!(OpenAiProvider::delta_text(&last), None);
+        assert_eq!(OpenAiProvider::usage(&last).output_tokens, 3);
+        assert_eq!(OpenAiProvider::usage(&chunk).input_tokens, 0);
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+
+use super::{LlmError, LlmRequest, LlmResponse};
+
+const MAX_RETRIES: u32 = 4;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Give up instead of honouring a `Retry-After` longer than this.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// Per-provider throughput limits.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Limits {
+    pub max_concurrent: usize,
+    /// `None` disables rate limiting.
+    pub requests_per_minute: Option<f64>,
+}
+
+impl Limits {
+    /// Gemini's free tier allows 15 requests per minute; Ollama serves one
+    /// request at a time anyway.
+    pub fn default_for(provider: &str) -> Self {
+        match provider {
+            "gemini" => Limits { max_concurrent: 2, requests_per_minute: Some(15.0) },
+            "ollama" => Limits { max_concurrent: 1, requests_per_minute: None },
+            _ => Limits { max_concurrent: 4, requests_per_minute: Some(60.0) },
+        }
+    }
+}
+
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    per_sec: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: f64) -> Self {
+        // Bursts of up to a tenth of the minute's budget.
+        let capacity = (requests_per_minute / 10.0).max(1.0);
+        TokenBucket { capacity, tokens: capacity, per_sec: requests_per_minute / 60.0, updated: Instant::now() }
+    }
+
+    /// Take a token, or return how long until one is available.
+    fn take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let refill = now.duration_since(self.updated).as_secs_f64() * self.per_sec;
+        self.tokens = (self.tokens + refill).min(self.capacity);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.per_sec))
+        }
+    }
+}
+
+struct ProviderQueue {
+    limits: Limits,
+    slots: Arc<Semaphore>,
+    bucket: Option<Mutex<TokenBucket>>,
+}
+
+type Outcome = Option<Result<LlmResponse, LlmError>>;
+
+/// Owned by `LlmClient`: concurrency slots and rate limits per provider,
+/// retry backoff, deduplication of identical pending requests and stale
+/// groups.
+pub struct Scheduler {
+    queues: Mutex<HashMap<String, Arc<ProviderQueue>>>,
+    pending: Mutex<HashMap<u64, watch::Receiver<Outcome>>>,
+    groups: Mutex<HashMap<String, watch::Sender<u64>>>,
+}
+
+/// A request's position in its stale group. Once a newer request joins the
+/// group, this one is dropped at its next wait (slot, rate limit or backoff);
+/// requests already talking to the provider run to completion.
+pub struct Ticket {
+    group: Option<(watch::Receiver<u64>, u64)>,
+}
+
+impl Ticket {
+    async fn superseded(&mut self) -> LlmError {
+        if let Some((rx, generation)) = &mut self.group {
+            loop {
+                if *rx.borrow_and_update() != *generation {
+                    return LlmError::Cancelled("superseded by a newer request".to_string());
+                }
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        }
+        std::future::pending().await
+    }
+
+    /// Await `fut` unless the ticket is superseded first.
+    async fn guard<T>(&mut self, fut: impl Future<Output = T>) -> Result<T, LlmError> {
+        tokio::select! {
+            value = fut => Ok(value),
+            e = self.superseded() => Err(e),
+        }
+    }
+}
+
+/// Removes a pending dedup entry when the leading request finishes or is dropped.
+struct PendingGuard<'a> {
+    pending: &'a Mutex<HashMap<u64, watch::Receiver<Outcome>>>,
+    key: u64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&self.key);
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler {
+            queues: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Join `group`, superseding every earlier request in it.
+    pub fn ticket(&self, group: Option<&str>) -> Ticket {
+        let Some(group) = group else { return Ticket { group: None } };
+        let mut groups = self.groups.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = groups.entry(group.to_string()).or_insert_with(|| watch::channel(0).0);
+        let generation = *tx.borrow() + 1;
+        tx.send_replace(generation);
+        Ticket { group: Some((tx.subscribe(), generation)) }
+    }
+
+    fn queue(&self, provider: &str, limits: Limits) -> Arc<ProviderQueue> {
+        let mut queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        match queues.get(provider) {
+            Some(queue) if queue.limits == limits => queue.clone(),
+            // New provider or changed settings. Permits held on a replaced
+            // queue are simply released into it.
+            _ => {
+                let queue = Arc::new(ProviderQueue {
+                    limits,
+                    slots: Arc::new(Semaphore::new(limits.max_concurrent.max(1))),
+                    bucket: limits.requests_per_minute.map(|rpm| Mutex::new(TokenBucket::new(rpm))),
+                });
+                queues.insert(provider.to_string(), queue.clone());
+                queue
+            }
+        }
+    }
+
+    /// Wait for a concurrency slot and a rate-limit token. The slot is
+    /// released when the returned permit is dropped.
+    pub async fn acquire(
+        &self,
+        provider: &str,
+        limits: Limits,
+        ticket: &mut Ticket,
+    ) -> Result<OwnedSemaphorePermit, LlmError> {
+        let queue = self.queue(provider, limits);
+        let permit = ticket
+            .guard(queue.slots.clone().acquire_owned())
+            .await?
+            .map_err(|_| LlmError::Cancelled("request queue closed".to_string()))?;
+
+        if let Some(bucket) = &queue.bucket {
+            loop {
+                let wait = match bucket.lock().unwrap_or_else(|e| e.into_inner()).take() {
+                    Ok(()) => break,
+                    Err(wait) => wait,
+                };
+                ticket.guard(tokio::time::sleep(wait)).await?;
+            }
+        }
+        Ok(permit)
+    }
+
+    /// After failed attempt number `attempt` (0-based), either sleep before the
+    /// next one or hand back the error if it is final.
+    pub async fn backoff(&self, attempt: u32, error: LlmError, ticket: &mut Ticket) -> Result<(), LlmError> {
+        if attempt >= MAX_RETRIES || !error.is_retryable() {
+            return Err(error);
+        }
+        let wait = match &error {
+            LlmError::Http { retry_after: Some(after), .. } if *after > MAX_RETRY_AFTER => return Err(error),
+            LlmError::Http { retry_after: Some(after), .. } => *after,
+            _ => exponential_backoff(attempt),
+        };
+        println!("[LLM] ⟳ Retry {}/{} in {:.1}s: {}", attempt + 1, MAX_RETRIES, wait.as_secs_f64(), error);
+        ticket.guard(tokio::time::sleep(wait)).await
+    }
+
+    /// Run `fut`, unless an identical request is already pending; then wait for
+    /// its result instead. The flag is true when the result was shared.
+    pub async fn dedup(
+        &self,
+        key: u64,
+        fut: impl Future<Output = Result<LlmResponse, LlmError>>,
+    ) -> (Result<LlmResponse, LlmError>, bool) {
+        let tx = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            match pending.get(&key) {
+                Some(rx) => Err(rx.clone()),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    pending.insert(key, rx);
+                    Ok(tx)
+                }
+            }
+        };
+
+        match tx {
+            Ok(tx) => {
+                let _guard = PendingGuard { pending: &self.pending, key };
+                let result = fut.await;
+                tx.send_replace(Some(result.clone()));
+                (result, false)
+            }
+            Err(mut rx) => {
+                println!("[LLM] Joined an identical pending request");
+                let result = match rx.wait_for(|outcome| outcome.is_some()).await {
+                    Ok(outcome) => outcome.clone().expect("wait_for guarantees an outcome"),
+                    Err(_) => Err(LlmError::Cancelled("the identical pending request was cancelled".to_string())),
+                };
+                (result, true)
+            }
+        }
+    }
+}
+
+/// Identity of a request for deduplication.
+pub fn request_key(provider: &str, model: &str, request: &LlmRequest) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    provider.hash(&mut hasher);
+    model.hash(&mut hasher);
+    serde_json::to_string(request).unwrap_or_default().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 1s, 2s, 4s, ... capped at `MAX_BACKOFF`, with up to 25% jitter so parallel
+/// retries don't line up.
+fn exponential_backoff(attempt: u32) -> Duration {
+    let base = BASE_BACKOFF.saturating_mul(1 << attempt.min(16)).min(MAX_BACKOFF);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    base.mul_f64(1.0 + (nanos % 1000) as f64 / 4000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Usage;
+
+    fn response(text: &str) -> LlmResponse {
+        LlmResponse {
+            provider: "test".to_string(),
+            model: "m".to_string(),
+            text: text.to_string(),
+            usage: Usage::default(),
+            shared: false,
+            cached: false,
+        }
+    }
+
+    fn http(status: u16, retry_after: Option<Duration>) -> LlmError {
+        LlmError::Http { status, body: String::new(), retry_after }
+    }
+
+    #[test]
+    fn bucket_allows_a_burst_then_refills_over_time() {
+        // 60 per minute: bursts of 6, one more per second.
+        let mut bucket = TokenBucket::new(60.0);
+        for _ in 0..6 {
+            assert!(bucket.take().is_ok());
+        }
+        let wait = bucket.take().unwrap_err();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1), "{:?}", wait);
+
+        bucket.updated -= Duration::from_secs(2);
+        assert!(bucket.take().is_ok());
+        assert!(bucket.take().is_ok());
+        assert!(bucket.take().is_err());
+
+        // A long idle period refills only up to the burst size.
+        bucket.updated -= Duration::from_secs(600);
+        assert_eq!((0..10).filter(|_| bucket.take().is_ok()).count(), 6);
+    }
+
+    #[test]
+    fn backoff_doubles_within_jitter_and_caps() {
+        for (attempt, base) in [(0, 1), (1, 2), (3, 8), (5, 30), (20, 30)] {
+            let wait = exponential_backoff(attempt);
+            let base = Duration::from_secs(base);
+            assert!(wait >= base && wait <= base.mul_f64(1.25), "attempt {}: {:?}", attempt, wait);
+        }
+    }
+
+    #[tokio::test]
+    async fn backoff_gives_up_on_final_or_overlong_waits() {
+        let scheduler = Scheduler::new();
+        let mut ticket = scheduler.ticket(None);
+        let too_long = http(429, Some(MAX_RETRY_AFTER + Duration::from_secs(1)));
+        assert!(scheduler.backoff(0, too_long, &mut ticket).await.is_err());
+        assert!(scheduler.backoff(0, http(400, None), &mut ticket).await.is_err());
+        assert!(scheduler.backoff(MAX_RETRIES, http(503, None), &mut ticket).await.is_err());
+        assert!(scheduler.backoff(0, http(429, Some(Duration::ZERO)), &mut ticket).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn identical_pending_request_shares_the_first_result() {
+        let scheduler = Scheduler::new();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let leader = scheduler.dedup(7, async {
+            rx.await.unwrap();
+            Ok(response("first"))
+        });
+        let follower = scheduler.dedup(7, async { Ok(response("second")) });
+        let ((first, first_shared), (second, second_shared), ()) = tokio::join!(leader, follower, async {
+            tx.send(()).unwrap();
+        });
+        assert_eq!((first.unwrap().text, first_shared), ("first".to_string(), false));
+        assert_eq!((second.unwrap().text, second_shared), ("first".to_string(), true));
+        // Finished requests are forgotten.
+        let (third, shared) = scheduler.dedup(7, async { Ok(response("third")) }).await;
+        assert_eq!((third.unwrap().text, shared), ("third".to_string(), false));
+    }
+
+    #[tokio::test]
+    async fn newer_ticket_in_a_group_supersedes_the_older() {
+        let scheduler = Scheduler::new();
+        let mut older = scheduler.ticket(Some("hint"));
+        let mut newer = scheduler.ticket(Some("hint"));
+        let mut other = scheduler.ticket(Some("chat"));
+
+        let result = older.guard(std::future::pending::<()>()).await;
+        assert!(matches!(result, Err(LlmError::Cancelled(_))));
+        assert_eq!(newer.guard(async { 1 }).await.unwrap(), 1);
+        assert_eq!(other.guard(async { 2 }).await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{record_exchange, LlmClient, LlmRequest, LlmResponse};
+
+/// Managed state: in-flight streaming requests by request ID.
+pub struct LlmStreams(Mutex<HashMap<String, JoinHandle<()>>>);
+
+impl LlmStreams {
+    pub fn new() -> Self {
+        LlmStreams(Mutex::new(HashMap::new()))
+    }
+}
+
+/// `llm-delta`: a piece of response text.
+#[derive(Serialize, Clone)]
+struct DeltaEvent<'a> {
+    request_id: &'a str,
+    delta: &'a str,
+}
+
+/// `llm-done`: the full response once the stream has finished.
+#[derive(Serialize, Clone)]
+struct DoneEvent<'a> {
+    request_id: &'a str,
+    response: &'a LlmResponse,
+}
+
+/// `llm-error`: the request failed; no further events follow.
+#[derive(Serialize, Clone)]
+struct ErrorEvent<'a> {
+    request_id: &'a str,
+    error: String,
+}
+
+/// Start a streaming request in the background. Progress is reported through
+/// `llm-delta`, then exactly one of `llm-done` / `llm-error`, all tagged with
+/// `request_id`. A cancelled request emits nothing further.
+pub fn start(app: &AppHandle, request_id: String, request: LlmRequest) -> Result<(), String> {
+    let streams = app.state::<LlmStreams>();
+    // Held until the handle is stored, so the task can't remove itself first.
+    let mut map = streams.0.lock().map_err(|e| format!("Stream registry poisoned: {}", e))?;
+    if map.contains_key(&request_id) {
+        return Err(format!("Request {} is already running", request_id));
+    }
+
+    let task_app = app.clone();
+    let id = request_id.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        let client = task_app.state::<LlmClient>();
+        let mut on_delta = |delta: &str| {
+            let _ = task_app.emit("llm-delta", DeltaEvent { request_id: &id, delta });
+        };
+        let result = client.stream(&task_app, &request, &mut on_delta).await;
+
+        if let Ok(mut map) = task_app.state::<LlmStreams>().0.lock() {
+            map.remove(&id);
+        }
+        match result {
+            Ok(response) => {
+                record_exchange(&task_app, &request, &response);
+                let _ = task_app.emit("llm-done", DoneEvent { request_id: &id, response: &response });
+            }
+            Err(e) => {
+                let _ = task_app.emit("llm-error", ErrorEvent { request_id: &id, error: e.to_string() });
+            }
+        }
+    });
+    map.insert(request_id, handle);
+    Ok(())
+}
+
+/// Abort an in-flight request. Returns false if it had already finished.
+pub fn cancel(app: &AppHandle, request_id: &str) -> bool {
+    let handle = app.state::<LlmStreams>().0.lock().ok().and_then(|mut map| map.remove(request_id));
+    match handle {
+        Some(handle) => {
+            handle.abort();
+            println!("[LLM] Cancelled {}", request_id);
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,405 @@
+use base64::{engine::general_purpose, Engine as _};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::cache::CacheStats;
+use super::{LlmError, LlmRequest, LlmResponse, Part};
+use crate::sessions::{now_ms, SessionStore};
+use crate::settings;
+
+/// USD per million input and output tokens. A model ID matches its entry
+/// exactly or with a version or date suffix, so `gemini-2.0-flash` also
+/// covers `gemini-2.0-flash-001` and `gpt-4o` covers `gpt-4o-2024-08-06`, but
+/// not `gpt-4o-mini`. Entries in the `llm_prices` setting override and extend
+/// this list.
+const DEFAULT_PRICES: &[(&str, f64, f64)] = &[
+    ("gemini-1.5-flash", 0.075, 0.30),
+    ("gemini-1.5-flash-8b", 0.0375, 0.15),
+    ("gemini-1.5-pro", 1.25, 5.00),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.0-flash-lite", 0.075, 0.30),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-pro", 1.25, 10.00),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4.1-mini", 0.40, 1.60),
+];
+
+/// How many days `report` breaks down by day.
+const REPORT_DAYS: i64 = 30;
+const REPORT_SESSIONS: usize = 50;
+
+const TOTALS: &str = "COUNT(*), COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0),
+    COALESCE(SUM(audio_seconds), 0), COALESCE(SUM(images), 0), COALESCE(SUM(cost), 0),
+    COALESCE(SUM(cost IS NULL), 0)";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Price {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PriceEntry {
+    pub model: String,
+    #[serde(flatten)]
+    pub price: Price,
+    /// Set in Settings rather than built in.
+    pub custom: bool,
+}
+
+/// One finished model request.
+pub struct UsageRecord {
+    pub provider: String,
+    pub model: String,
+    pub kind: Option<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub audio_seconds: f64,
+    pub images: u32,
+    /// USD; `None` when the model has no price.
+    pub cost: Option<f64>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct UsageTotal {
+    /// Session ID, `YYYY-MM-DD` or provider name, depending on the breakdown.
+    pub key: String,
+    /// Session title for the per-session breakdown.
+    pub label: Option<String>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub audio_seconds: f64,
+    pub images: u64,
+    pub cost: f64,
+    /// Requests to models without a price; not included in `cost`.
+    pub unpriced: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BudgetStatus {
+    /// `day` or `session`.
+    pub period: String,
+    pub spent: f64,
+    pub soft: Option<f64>,
+    pub hard: Option<f64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct UsageReport {
+    pub total: UsageTotal,
+    /// Most recent sessions first.
+    pub by_session: Vec<UsageTotal>,
+    /// The last 30 days, newest first.
+    pub by_day: Vec<UsageTotal>,
+    pub by_provider: Vec<UsageTotal>,
+    pub budget: BudgetStatus,
+    /// Response cache hits and misses.
+    pub cache: Option<CacheStats>,
+}
+
+#[derive(Serialize, Clone)]
+struct BudgetEvent<'a> {
+    /// `soft` or `hard`.
+    level: &'a str,
+    status: &'a BudgetStatus,
+}
+
+/// The price table: built-in defaults merged with the `llm_prices` setting.
+pub fn prices(app: &AppHandle) -> Vec<PriceEntry> {
+    let mut table: BTreeMap<String, PriceEntry> = DEFAULT_PRICES
+        .iter()
+        .map(|(model, input, output)| {
+            let price = Price { input_per_million: *input, output_per_million: *output };
+            (model.to_string(), PriceEntry { model: model.to_string(), price, custom: false })
+        })
+        .collect();
+    for (model, price) in custom_prices(app) {
+        table.insert(model.clone(), PriceEntry { model, price, custom: true });
+    }
+    table.into_values().collect()
+}
+
+/// Set the price of `model`, or drop the custom entry when `price` is `None`.
+pub fn set_price(app: &AppHandle, model: &str, price: Option<Price>) -> Result<Vec<PriceEntry>, String> {
+    let model = model.trim();
+    if model.is_empty() {
+        return Err("Model cannot be empty".to_string());
+    }
+    let mut custom = custom_prices(app);
+    match price {
+        Some(p) if p.input_per_million < 0.0 || p.output_per_million < 0.0 => {
+            return Err("Prices cannot be negative".to_string());
+        }
+        Some(p) => {
+            custom.insert(model.to_string(), p);
+        }
+        None => {
+            custom.remove(model);
+        }
+    }
+    settings::set(app, "llm_prices", &custom)?;
+    Ok(prices(app))
+}
+
+fn custom_prices(app: &AppHandle) -> BTreeMap<String, Price> {
+    settings::get(app, "llm_prices").unwrap_or_default()
+}
+
+/// Price for `model`; local Ollama models are free.
+fn price_for(app: &AppHandle, provider: &str, model: &str) -> Option<Price> {
+    if provider == "ollama" {
+        return Some(Price { input_per_million: 0.0, output_per_million: 0.0 });
+    }
+    let model = model.strip_prefix("models/").unwrap_or(model);
+    prices(app)
+        .into_iter()
+        .filter(|e| is_version_of(model, &e.model))
+        .max_by_key(|e| e.model.len())
+        .map(|e| e.price)
+}
+
+/// `model` is `base` or a dated or numbered release of it. Other suffixes
+/// (`-mini`, `-nano`, `-lite`) name different, usually cheaper, models.
+fn is_version_of(model: &str, base: &str) -> bool {
+    match model.strip_prefix(base) {
+        Some("") => true,
+        Some(rest) => rest
+            .strip_prefix('-')
+            .is_some_and(|v| v.starts_with(|c: char| c.is_ascii_digit()) && v.chars().all(|c| c.is_ascii_digit() || c == '-')),
+        None => false,
+    }
+}
+
+/// Record the usage of a finished request and warn when it crosses a budget.
+/// Shared and cached responses were already recorded by the request that
+/// made them.
+pub fn record(app: &AppHandle, request: &LlmRequest, response: &LlmResponse) {
+    if response.shared || response.cached {
+        return;
+    }
+    let Some(sessions) = app.try_state::<SessionStore>() else { return };
+
+    let (audio_seconds, images) = media_stats(&request.parts);
+    let cost = price_for(app, &response.provider, &response.model).map(|p| {
+        (response.usage.input_tokens as f64 * p.input_per_million
+            + response.usage.output_tokens as f64 * p.output_per_million)
+            / 1_000_000.0
+    });
+    let record = UsageRecord {
+        provider: response.provider.clone(),
+        model: response.model.clone(),
+        kind: request.kind.clone(),
+        input_tokens: response.usage.input_tokens,
+        output_tokens: response.usage.output_tokens,
+        audio_seconds,
+        images,
+        cost,
+    };
+
+    let before = budget_status(app);
+    if let Err(e) = sessions.add_usage(&record) {
+        eprintln!("[Usage] ✗ {}", e);
+        return;
+    }
+    match cost {
+        Some(cost) => println!("[Usage] {} {}: ${:.5}", record.provider, record.model, cost),
+        None => println!("[Usage] {} {}: no price, cost not counted", record.provider, record.model),
+    }
+
+    let after = BudgetStatus { spent: before.spent + cost.unwrap_or(0.0), ..before.clone() };
+    for (level, limit) in [("soft", after.soft), ("hard", after.hard)] {
+        if limit.is_some_and(|l| before.spent < l && after.spent >= l) {
+            eprintln!("[Usage] {} budget reached: ${:.2} this {}", level, after.spent, after.period);
+            let _ = app.emit("llm-budget", BudgetEvent { level, status: &after });
+        }
+    }
+}
+
+/// Refuse new requests once the hard budget is spent.
+pub fn check_budget(app: &AppHandle) -> Result<(), LlmError> {
+    let status = budget_status(app);
+    match status.hard {
+        Some(hard) if status.spent >= hard => Err(LlmError::Budget(format!(
+            "Budget of ${:.2} per {} reached (${:.2} spent)",
+            hard, status.period, status.spent
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Spending in the current budget period, with the `llm_budget_soft` and
+/// `llm_budget_hard` limits (USD). `llm_budget_period` is `day` (default) or
+/// `session`.
+pub fn budget_status(app: &AppHandle) -> BudgetStatus {
+    let period = match settings::get::<String>(app, "llm_budget_period").as_deref() {
+        Some("session") => "session",
+        _ => "day",
+    };
+    let limit = |key| settings::get::<f64>(app, key).filter(|v| *v > 0.0);
+    let spent = app
+        .try_state::<SessionStore>()
+        .map(|sessions| sessions.usage_spent(period == "session"))
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("[Usage] ✗ {}", e);
+            None
+        })
+        .unwrap_or(0.0);
+    BudgetStatus {
+        period: period.to_string(),
+        spent,
+        soft: limit("llm_budget_soft"),
+        hard: limit("llm_budget_hard"),
+    }
+}
+
+/// Seconds of WAV audio and the number of images in a request.
+fn media_stats(parts: &[Part]) -> (f64, u32) {
+    let mut seconds = 0.0;
+    let mut images = 0;
+    for part in parts {
+        match part {
+            Part::Image { .. } => images += 1,
+            Part::Audio { mime_type, data } if mime_type.contains("wav") => {
+                let Ok(bytes) = general_purpose::STANDARD.decode(data) else { continue };
+                if let Ok(reader) = hound::WavReader::new(std::io::Cursor::new(bytes)) {
+                    seconds += reader.duration() as f64 / reader.spec().sample_rate as f64;
+                }
+            }
+            _ => {}
+        }
+    }
+    (seconds, images)
+}
+
+pub fn insert(conn: &Connection, session_id: Option<i64>, record: &UsageRecord) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO usage (session_id, created_at, day, provider, model, kind,
+                            input_tokens, output_tokens, audio_seconds, images, cost)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            session_id,
+            now_ms(),
+            today(),
+            record.provider,
+            record.model,
+            record.kind,
+            record.input_tokens as i64,
+            record.output_tokens as i64,
+            record.audio_seconds,
+            record.images,
+            record.cost,
+        ],
+    )
+    .map_err(|e| format!("Failed to store usage: {}", e))?;
+    Ok(())
+}
+
+/// USD spent today, or in `session_id` when given.
+pub fn spent(conn: &Connection, session_id: Option<i64>) -> Result<f64, String> {
+    let result = match session_id {
+        Some(id) => conn.query_row("SELECT COALESCE(SUM(cost), 0) FROM usage WHERE session_id = ?1", [id], |r| r.get(0)),
+        None => conn.query_row("SELECT COALESCE(SUM(cost), 0) FROM usage WHERE day = ?1", [today()], |r| r.get(0)),
+    };
+    result.map_err(|e| format!("Failed to read usage: {}", e))
+}
+
+pub fn report(conn: &Connection, budget: BudgetStatus) -> Result<UsageReport, String> {
+    let first_day = (chrono::Local::now() - chrono::Duration::days(REPORT_DAYS - 1))
+        .format("%Y-%m-%d")
+        .to_string();
+    let total = totals(conn, &format!("SELECT '', NULL, {} FROM usage", TOTALS), params![])?
+        .pop()
+        .unwrap_or_default();
+    let by_session = totals(
+        conn,
+        &format!(
+            "SELECT CAST(u.session_id AS TEXT), s.title, {} FROM usage u JOIN sessions s ON s.id = u.session_id
+             GROUP BY u.session_id ORDER BY MAX(u.created_at) DESC LIMIT ?1",
+            TOTALS
+        ),
+        params![REPORT_SESSIONS as i64],
+    )?;
+    let by_day = totals(
+        conn,
+        &format!("SELECT day, NULL, {} FROM usage WHERE day >= ?1 GROUP BY day ORDER BY day DESC", TOTALS),
+        params![first_day],
+    )?;
+    let by_provider = totals(
+        conn,
+        &format!("SELECT provider, NULL, {} FROM usage GROUP BY provider ORDER BY SUM(cost) DESC", TOTALS),
+        params![],
+    )?;
+    Ok(UsageReport { total, by_session, by_day, by_provider, budget, cache: None })
+}
+
+fn totals(conn: &Connection, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<UsageTotal>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("Failed to query usage: {}", e))?;
+    let rows = stmt
+        .query_map(params, |r| {
+            Ok(UsageTotal {
+                key: r.get(0)?,
+                label: r.get(1)?,
+                requests: r.get::<_, i64>(2)? as u64,
+                input_tokens: r.get::<_, i64>(3)? as u64,
+                output_tokens: r.get::<_, i64>(4)? as u64,
+                audio_seconds: r.get(5)?,
+                images: r.get::<_, i64>(6)? as u64,
+                cost: r.get(7)?,
+                unpriced: r.get::<_, i64>(8)? as u64,
+            })
+        })
+        .map_err(|e| format!("Failed to query usage: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read usage row: {}", e))
+}
+
+/// Local calendar day, so daily totals match the user's clock.
+fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The default-price model `model` is billed as, as `price_for` picks it.
+    fn billed_as(model: &str) -> Option<&'static str> {
+        DEFAULT_PRICES
+            .iter()
+            .map(|(name, _, _)| *name)
+            .filter(|name| is_version_of(model, name))
+            .max_by_key(|name| name.len())
+    }
+
+    #[test]
+    fn exact_and_dated_names_match() {
+        assert!(is_version_of("gpt-4o", "gpt-4o"));
+        assert!(is_version_of("gpt-4o-2024-08-06", "gpt-4o"));
+        assert!(is_version_of("gemini-2.0-flash-001", "gemini-2.0-flash"));
+    }
+
+    #[test]
+    fn other_models_with_the_same_prefix_dont() {
+        assert!(!is_version_of("gpt-4o-mini", "gpt-4o"));
+        assert!(!is_version_of("gpt-4o-mini-2024-07-18", "gpt-4o"));
+        assert!(!is_version_of("gpt-4o-realtime-preview", "gpt-4o"));
+        assert!(!is_version_of("gpt-4o2", "gpt-4o"));
+        assert!(!is_version_of("gpt-4", "gpt-4o"));
+    }
+
+    #[test]
+    fn the_most_specific_default_price_applies() {
+        assert_eq!(billed_as("gpt-4o-2024-08-06"), Some("gpt-4o"));
+        assert_eq!(billed_as("gpt-4o-mini-2024-07-18"), Some("gpt-4o-mini"));
+        assert_eq!(billed_as("gpt-4o-mini"), Some("gpt-4o-mini"));
+        assert_eq!(billed_as("gemini-1.5-flash-8b-001"), Some("gemini-1.5-flash-8b"));
+        assert_eq!(billed_as("gemini-2.0-flash-lite-001"), Some("gemini-2.0-flash-lite"));
+        assert_eq!(billed_as("gemini-2.5-flash-preview"), None);
+    }
+}
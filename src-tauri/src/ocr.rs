@@ -0,0 +1,234 @@
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+
+use crate::code::{self, CodeBlock};
+use crate::context::estimate_tokens;
+use crate::settings;
+
+const DETECTION_MODEL: &str = "text-detection.rten";
+const RECOGNITION_MODEL: &str = "text-recognition.rten";
+/// OCR output shorter than this is treated as "no text" when deciding what to send.
+const MIN_TEXT_CHARS: usize = 20;
+
+/// One recognized line of text. Coordinates are pixels of the OCR'd image.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TextBlock {
+    pub text: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Mean text-detection probability over the block (0.0 – 1.0).
+    pub confidence: f32,
+    #[serde(default)]
+    pub words: Vec<Word>,
+}
+
+/// A word of a `TextBlock` and its horizontal extent.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Word {
+    pub text: String,
+    pub x: u32,
+    pub width: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct OcrResult {
+    /// All blocks, one per line, in reading order, with code regions as
+    /// fenced Markdown blocks.
+    pub text: String,
+    pub blocks: Vec<TextBlock>,
+    pub code: Vec<CodeBlock>,
+    pub width: u32,
+    pub height: u32,
+    pub elapsed_ms: u64,
+}
+
+/// Managed state: the OCR engine, loaded on first use.
+pub struct LocalOcr(Mutex<Option<Arc<engine::Engine>>>);
+
+impl LocalOcr {
+    pub fn new() -> Self {
+        LocalOcr(Mutex::new(None))
+    }
+}
+
+/// Recognize the text in `image`. Blocks while the models load on first use
+/// and during recognition, so call it off the async runtime.
+pub fn recognize(app: &AppHandle, image: &RgbaImage) -> Result<OcrResult, String> {
+    let engine = load(app)?;
+    let started = Instant::now();
+    let blocks = engine.recognize(image)?;
+    let layout = code::reconstruct(&blocks);
+    let result = OcrResult {
+        text: layout.text,
+        blocks,
+        code: layout.code,
+        width: image.width(),
+        height: image.height(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    };
+    println!(
+        "[OCR] ✓ {} lines, {} code blocks in {} ms",
+        result.blocks.len(),
+        result.code.len(),
+        result.elapsed_ms
+    );
+    Ok(result)
+}
+
+/// Whether `recognize` can run: the `local-ocr` feature is built and the
+/// models are loaded or downloaded. Doesn't load them.
+pub fn available(app: &AppHandle) -> bool {
+    if !cfg!(feature = "local-ocr") {
+        return false;
+    }
+    if app.state::<LocalOcr>().0.lock().unwrap().is_some() {
+        return true;
+    }
+    models_dir(app).is_ok_and(|dir| dir.join(DETECTION_MODEL).is_file() && dir.join(RECOGNITION_MODEL).is_file())
+}
+
+fn load(app: &AppHandle) -> Result<Arc<engine::Engine>, String> {
+    let state = app.state::<LocalOcr>();
+    let mut guard = state.0.lock().unwrap();
+    if let Some(engine) = guard.as_ref() {
+        return Ok(engine.clone());
+    }
+    let dir = models_dir(app)?;
+    let (detection, recognition) = (dir.join(DETECTION_MODEL), dir.join(RECOGNITION_MODEL));
+    println!("[OCR] Loading models from {}", dir.display());
+    let engine = Arc::new(engine::Engine::load(&detection, &recognition)?);
+    println!("[OCR] ✓ Models loaded");
+    *guard = Some(engine.clone());
+    Ok(engine)
+}
+
+fn models_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("models"))
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))
+}
+
+/// Whether to send OCR text instead of the image, per `screenshot_send`:
+/// `image` (default), `text` whenever there is text, or `auto` when the text
+/// is estimated to cost fewer tokens than the image.
+pub fn prefer_text(app: &AppHandle, text: &str, image_width: u32, image_height: u32) -> bool {
+    let has_text = text.chars().filter(|c| !c.is_whitespace()).count() >= MIN_TEXT_CHARS;
+    match settings::get::<String>(app, "screenshot_send").as_deref() {
+        Some("text") => has_text,
+        Some("auto") => has_text && estimate_tokens(text) < estimate_image_tokens(image_width, image_height),
+        _ => false,
+    }
+}
+
+/// Rough image cost in the style of Gemini: 258 tokens per 768×768 tile.
+fn estimate_image_tokens(width: u32, height: u32) -> usize {
+    (width.div_ceil(768).max(1) * height.div_ceil(768).max(1)) as usize * 258
+}
+
+#[cfg(feature = "local-ocr")]
+mod engine {
+    use super::{TextBlock, Word};
+    use image::RgbaImage;
+    use ocrs::{ImageSource, OcrEngine, OcrEngineParams, TextItem};
+    use rten::Model;
+    use std::path::Path;
+
+    pub struct Engine {
+        ocr: OcrEngine,
+    }
+
+    impl Engine {
+        pub fn load(detection: &Path, recognition: &Path) -> Result<Self, String> {
+            let load = |path: &Path| {
+                Model::load_file(path).map_err(|e| format!("Failed to load {}: {}", path.display(), e))
+            };
+            let ocr = OcrEngine::new(OcrEngineParams {
+                detection_model: Some(load(detection)?),
+                recognition_model: Some(load(recognition)?),
+                ..Default::default()
+            })
+            .map_err(|e| format!("Failed to start OCR engine: {}", e))?;
+            Ok(Engine { ocr })
+        }
+
+        pub fn recognize(&self, image: &RgbaImage) -> Result<Vec<TextBlock>, String> {
+            let source = ImageSource::from_bytes(image.as_raw(), image.dimensions())
+                .map_err(|e| format!("Invalid OCR input: {}", e))?;
+            let input = self.ocr.prepare_input(source).map_err(|e| e.to_string())?;
+            // Detection runs once more for the probability map the confidence comes from.
+            let probabilities = self.ocr.detect_text_pixels(&input).map_err(|e| e.to_string())?;
+            let words = self.ocr.detect_words(&input).map_err(|e| e.to_string())?;
+            let lines = self.ocr.find_text_lines(&input, &words);
+            let recognized = self.ocr.recognize_text(&input, &lines).map_err(|e| e.to_string())?;
+
+            let (width, height) = image.dimensions();
+            Ok(recognized
+                .into_iter()
+                .flatten()
+                .filter_map(|line| {
+                    let text = line.to_string().trim().to_string();
+                    if text.is_empty() {
+                        return None;
+                    }
+                    let rect = line.bounding_rect();
+                    let x = rect.left().clamp(0, width as i32) as u32;
+                    let y = rect.top().clamp(0, height as i32) as u32;
+                    let right = rect.right().clamp(0, width as i32) as u32;
+                    let bottom = rect.bottom().clamp(0, height as i32) as u32;
+
+                    let mut sum = 0.0;
+                    for py in y..bottom {
+                        for px in x..right {
+                            sum += probabilities[[py as usize, px as usize]];
+                        }
+                    }
+                    let area = ((right - x) * (bottom - y)).max(1) as f32;
+                    let words = line
+                        .words()
+                        .map(|word| {
+                            let rect = word.bounding_rect();
+                            let left = rect.left().clamp(0, width as i32) as u32;
+                            let right = rect.right().clamp(0, width as i32) as u32;
+                            Word { text: word.to_string(), x: left, width: right - left }
+                        })
+                        .collect();
+                    Some(TextBlock {
+                        text,
+                        x,
+                        y,
+                        width: right - x,
+                        height: bottom - y,
+                        confidence: (sum / area).clamp(0.0, 1.0),
+                        words,
+                    })
+                })
+                .collect())
+        }
+    }
+}
+
+#[cfg(not(feature = "local-ocr"))]
+mod engine {
+    use super::TextBlock;
+    use image::RgbaImage;
+    use std::path::Path;
+
+    pub struct Engine;
+
+    impl Engine {
+        pub fn load(_detection: &Path, _recognition: &Path) -> Result<Self, String> {
+            Err("Local OCR is not available in this build (enable the `local-ocr` feature)".to_string())
+        }
+
+        pub fn recognize(&self, _image: &RgbaImage) -> Result<Vec<TextBlock>, String> {
+            unreachable!("Engine cannot be constructed without the `local-ocr` feature")
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::context;
+use crate::llm::{LlmRequest, Part};
+use crate::sessions::{now_ms, SessionStore};
+use crate::settings;
+
+/// Built-in templates: name, description and default text.
+const BUILTIN: &[(&str, &str, &str)] = &[
+    ("hint", "Answer to an interviewer question heard in the audio", include_str!("../prompts/hint.md")),
+    ("screenshot", "Analysis of a captured screenshot", include_str!("../prompts/screenshot.md")),
+    ("chat", "Chat message with the session context", include_str!("../prompts/chat.md")),
+    ("summary", "Condenses older transcript into rolling context", include_str!("../prompts/summary.md")),
+];
+
+const DEFAULT_LANGUAGE: &str = "русский";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PromptVersion {
+    pub version: u32,
+    pub body: String,
+    /// Unix ms; 0 for a built-in default that was never saved.
+    pub saved_at: i64,
+    /// `default` for built-in text, `user` for edits.
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub description: String,
+    pub builtin: bool,
+    /// Oldest first; the last entry is the current text.
+    pub versions: Vec<PromptVersion>,
+}
+
+impl PromptTemplate {
+    fn current(&self) -> &PromptVersion {
+        self.versions.last().expect("templates always have a version")
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PromptSummary {
+    pub name: String,
+    pub description: String,
+    pub builtin: bool,
+    pub version: u32,
+    /// Differs from the built-in default.
+    pub modified: bool,
+    pub variables: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RenderedPrompt {
+    pub name: String,
+    pub version: u32,
+    pub text: String,
+    /// Values the template was rendered with.
+    pub variables: HashMap<String, String>,
+    /// Referenced by the template but not available.
+    pub missing: Vec<String>,
+}
+
+/// Managed state: prompt templates as one JSON file each under
+/// `<app data>/prompts`. Built-ins without a file use their default text.
+pub struct PromptStore {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl PromptStore {
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        let dir = data_dir.join("prompts");
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        Ok(PromptStore { dir, lock: Mutex::new(()) })
+    }
+
+    pub fn list(&self) -> Result<Vec<PromptSummary>, String> {
+        let mut names: Vec<String> = BUILTIN.iter().map(|(name, _, _)| name.to_string()).collect();
+        let entries = std::fs::read_dir(&self.dir).map_err(|e| format!("Failed to read prompts: {}", e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    if !names.iter().any(|n| n == stem) {
+                        names.push(stem.to_string());
+                    }
+                }
+            }
+        }
+
+        names
+            .iter()
+            .map(|name| {
+                let template = self.get(name)?;
+                let current = template.current();
+                Ok(PromptSummary {
+                    modified: builtin(name).is_some_and(|(_, default)| default != current.body),
+                    variables: variables(&current.body),
+                    name: template.name.clone(),
+                    description: template.description.clone(),
+                    builtin: template.builtin,
+                    version: current.version,
+                })
+            })
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Result<PromptTemplate, String> {
+        validate_name(name)?;
+        let path = self.path(name);
+        if path.exists() {
+            let json = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            return serde_json::from_str(&json).map_err(|e| format!("Failed to parse {}: {}", path.display(), e));
+        }
+        let (description, body) = builtin(name).ok_or_else(|| format!("Prompt '{}' not found", name))?;
+        Ok(PromptTemplate {
+            name: name.to_string(),
+            description: description.to_string(),
+            builtin: true,
+            versions: vec![PromptVersion { version: 1, body: body.to_string(), saved_at: 0, source: "default".into() }],
+        })
+    }
+
+    /// Save `body` as the next version, creating a custom template if needed.
+    pub fn save(&self, name: &str, body: &str, description: Option<String>) -> Result<PromptTemplate, String> {
+        let _lock = self.lock.lock().unwrap();
+        let mut template = match self.get(name) {
+            Ok(template) => template,
+            Err(_) => {
+                validate_name(name)?;
+                PromptTemplate { name: name.to_string(), description: String::new(), builtin: false, versions: Vec::new() }
+            }
+        };
+        if let Some(description) = description {
+            template.description = description;
+        }
+        push_version(&mut template, body, "user");
+        self.write(&template)?;
+        println!("[Prompts] Saved '{}' v{}", name, template.current().version);
+        Ok(template)
+    }
+
+    /// Make the built-in default current again. History is kept.
+    pub fn reset(&self, name: &str) -> Result<PromptTemplate, String> {
+        let _lock = self.lock.lock().unwrap();
+        let (_, default) = builtin(name).ok_or_else(|| format!("'{}' is not a built-in prompt", name))?;
+        let mut template = self.get(name)?;
+        push_version(&mut template, default, "default");
+        self.write(&template)?;
+        println!("[Prompts] Reset '{}' to default (v{})", name, template.current().version);
+        Ok(template)
+    }
+
+    /// Delete a custom template. Built-ins can only be reset.
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        validate_name(name)?;
+        if builtin(name).is_some() {
+            return Err(format!("'{}' is built in; reset it instead", name));
+        }
+        let path = self.path(name);
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", path.display(), e))
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    fn write(&self, template: &PromptTemplate) -> Result<(), String> {
+        let path = self.path(&template.name);
+        let json = serde_json::to_string_pretty(template).map_err(|e| format!("Failed to serialize prompt: {}", e))?;
+        std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+fn builtin(name: &str) -> Option<(&'static str, &'static str)> {
+    BUILTIN.iter().find(|(n, _, _)| *n == name).map(|(_, description, body)| (*description, *body))
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid prompt name '{}': use lowercase letters, digits, '-' and '_'", name))
+    }
+}
+
+fn push_version(template: &mut PromptTemplate, body: &str, source: &str) {
+    let version = template.versions.last().map_or(1, |v| v.version + 1);
+    // Drop the synthesized, never-saved default so history only has real saves.
+    template.versions.retain(|v| v.saved_at != 0);
+    template.versions.push(PromptVersion {
+        version,
+        body: body.to_string(),
+        saved_at: now_ms(),
+        source: source.to_string(),
+    });
+}
+
+/// Values for the standard variables, taken from Settings and the active
+/// session: `language`, `notes`, `summary`, `transcript` and `screenshot_text`.
+pub fn context_variables(app: &AppHandle) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    let language: Option<String> = settings::get(app, "prompt_language");
+    vars.insert(
+        "language".to_string(),
+        language.filter(|l| !l.trim().is_empty()).unwrap_or_else(|| DEFAULT_LANGUAGE.to_string()),
+    );
+    if let Some(notes) = settings::get::<String>(app, "session_notes") {
+        vars.insert("notes".to_string(), notes);
+    }
+    let context = context::build(app);
+    vars.insert("summary".to_string(), context.summary);
+    vars.insert("transcript".to_string(), context.transcript);
+    if let Some(sessions) = app.try_state::<SessionStore>() {
+        if let Ok(Some(text)) = sessions.latest_screenshot_text() {
+            vars.insert("screenshot_text".to_string(), text);
+        }
+    }
+    vars
+}
+
+/// Render the current version of template `name`. `overrides` take
+/// precedence over the context variables.
+pub fn render_named(app: &AppHandle, name: &str, overrides: &HashMap<String, String>) -> Result<RenderedPrompt, String> {
+    let template = app.state::<PromptStore>().get(name)?;
+    let current = template.current();
+    Ok(render_body(app, name, current.version, &current.body, overrides))
+}
+
+/// Render `body` as if it were the current text of `name`, for previewing edits.
+pub fn render_body(
+    app: &AppHandle,
+    name: &str,
+    version: u32,
+    body: &str,
+    overrides: &HashMap<String, String>,
+) -> RenderedPrompt {
+    let mut vars = context_variables(app);
+    vars.extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+    let (text, missing) = render(body, &vars);
+    let used = variables(body);
+    vars.retain(|k, _| used.contains(k));
+    RenderedPrompt { name: name.to_string(), version, text, variables: vars, missing }
+}
+
+/// Expand `request.template` into a leading text part.
+pub fn apply(app: &AppHandle, request: &mut LlmRequest) -> Result<(), String> {
+    let Some(name) = request.template.take() else { return Ok(()) };
+    let rendered = render_named(app, &name, &request.variables)?;
+    request.parts.insert(0, Part::Text { text: rendered.text });
+    Ok(())
+}
+
+/// Substitute `{{name}}` and keep `{{#name}}…{{/name}}` sections only when
+/// `name` is non-empty. Returns the text and the referenced names that had
+/// no value.
+pub fn render(body: &str, vars: &HashMap<String, String>) -> (String, Vec<String>) {
+    let mut missing = Vec::new();
+    let out = expand(body, vars, &mut missing);
+    missing.sort();
+    missing.dedup();
+    (collapse_blank_lines(&out), missing)
+}
+
+fn expand(body: &str, vars: &HashMap<String, String>, missing: &mut Vec<String>) -> String {
+    let mut out = String::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+
+        if let Some(name) = tag.strip_prefix('#') {
+            let name = name.trim();
+            let close = format!("{{{{/{}}}}}", name);
+            let (inner, tail) = match rest.find(&close) {
+                Some(i) => (&rest[..i], &rest[i + close.len()..]),
+                None => (rest, ""),
+            };
+            if vars.get(name).is_some_and(|v| !v.trim().is_empty()) {
+                out.push_str(&expand(inner, vars, missing));
+            }
+            rest = tail;
+        } else if tag.starts_with('/') {
+            // Stray closing tag.
+        } else {
+            match vars.get(tag) {
+                Some(value) => out.push_str(value),
+                None => missing.push(tag.to_string()),
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Variable names referenced by a template, sorted.
+pub fn variables(body: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        let tag = after[..end].trim();
+        if !tag.starts_with('/') {
+            names.push(tag.trim_start_matches('#').trim().to_string());
+        }
+        rest = &after[end + 2..];
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Dropped sections leave runs of empty lines behind.
+fn collapse_blank_lines(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut newlines = 0;
+    for c in text.trim().chars() {
+        if c == '\n' {
+            newlines += 1;
+            if newlines > 2 {
+                continue;
+            }
+        } else {
+            newlines = 0;
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn missing_variables_are_reported_once_and_left_empty() {
+        let (text, missing) = render("Hi {{name}} in {{ lang }}, {{lang}}!", &vars(&[("name", "Ann")]));
+        assert_eq!(text, "Hi Ann in , !");
+        assert_eq!(missing, ["lang"]);
+    }
+
+    #[test]
+    fn empty_sections_are_dropped_with_their_blank_lines() {
+        let body = "Intro\n\n{{#notes}}Notes:\n{{notes}}\n{{/notes}}\n\n{{#summary}}Earlier: {{summary}}{{/summary}}\n\nEnd";
+        let (text, missing) = render(body, &vars(&[("notes", "  ")]));
+        assert_eq!(text, "Intro\n\nEnd");
+        // Variables inside a dropped section aren't missing.
+        assert!(missing.is_empty());
+
+        let (text, _) = render(body, &vars(&[("notes", "be brief"), ("summary", "arrays")]));
+        assert_eq!(text, "Intro\n\nNotes:\nbe brief\n\nEarlier: arrays\n\nEnd");
+    }
+
+    #[test]
+    fn sections_nest_and_unclosed_tags_run_to_the_end() {
+        let body = "{{#a}}A{{#b}} B={{b}}{{/b}}.{{/a}}";
+        assert_eq!(render(body, &vars(&[("a", "1"), ("b", "x")])).0, "A B=x.");
+        assert_eq!(render(body, &vars(&[("a", "1")])).0, "A.");
+        assert_eq!(render(body, &vars(&[("b", "x")])).0, "");
+        // Whitespace inside a kept section stays.
+        assert_eq!(render("Hello{{#name}} {{name}}{{/name}}!", &vars(&[("name", "Ann")])).0, "Hello Ann!");
+
+        assert_eq!(render("x {{#a}}A {{a}}", &vars(&[("a", "1")])).0, "x A 1");
+        assert_eq!(render("x {{#a}}A {{a}}", &vars(&[])).0, "x");
+        assert_eq!(render("x{{/a}}y", &vars(&[])).0, "xy");
+        assert_eq!(render("Hi {{name", &vars(&[("name", "Ann")])).0, "Hi {{name");
+    }
+
+    #[test]
+    fn variables_cover_every_placeholder_of_the_builtins() {
+        for (name, _, body) in BUILTIN {
+            let names = variables(body);
+            assert!(!names.is_empty(), "{}", name);
+            let all: HashMap<String, String> = names.iter().map(|n| (n.clone(), "value".to_string())).collect();
+            let (text, missing) = render(body, &all);
+            assert!(missing.is_empty(), "{}: {:?}", name, missing);
+            assert!(!text.contains("{{"), "{}", name);
+        }
+        assert_eq!(variables(builtin("hint").unwrap().1), ["language", "notes", "summary", "transcript"]);
+        assert_eq!(variables("{{#a}}{{ b }}{{/a}}{{a}}"), ["a", "b"]);
+    }
+
+    #[test]
+    fn blank_lines_collapse_to_one_empty_line() {
+        assert_eq!(collapse_blank_lines("\n\na\n\n\n\nb\nc\n"), "a\n\nb\nc");
+    }
+}
@@ -0,0 +1,400 @@
+use image::{Rgba, RgbaImage};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+
+use crate::llm::{LlmRequest, Part};
+use crate::ocr::{OcrResult, TextBlock};
+use crate::sessions::SessionStore;
+use crate::settings;
+
+/// Pixels added around a masked screenshot region.
+const MASK_PADDING: u32 = 2;
+
+/// A user-defined rule from the `redact_patterns` setting.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CustomPattern {
+    pub name: String,
+    pub pattern: String,
+}
+
+/// One redacted value. Only the rule and length are kept: even a hash of a
+/// phone or card number can be brute-forced back to the value.
+#[derive(Serialize, Clone, Debug)]
+pub struct Redaction {
+    pub rule: String,
+    pub length: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Redacted {
+    pub text: String,
+    pub redactions: Vec<Redaction>,
+}
+
+/// Extra check on a match, for patterns a regex alone over-matches.
+type Validate = fn(&str) -> bool;
+
+#[derive(Clone)]
+struct Rule {
+    name: String,
+    regex: Regex,
+    replacement: String,
+    validate: Option<Validate>,
+}
+
+struct Match<'r> {
+    rule: &'r Rule,
+    start: usize,
+    end: usize,
+}
+
+/// Built-in rules: name, pattern, replacement, validation.
+const BUILTIN: &[(&str, &str, &str, Option<Validate>)] = &[
+    ("email", r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b", "[EMAIL]", None),
+    (
+        "api_key",
+        r"\b(?:sk-[A-Za-z0-9_-]{20,}|AKIA[0-9A-Z]{16}|AIza[0-9A-Za-z_-]{35}|gh[pousr]_[A-Za-z0-9]{36,}|xox[abprs]-[A-Za-z0-9-]{10,}|eyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,})|(?i:bearer)\s+[A-Za-z0-9._~+/-]{20,}=*",
+        "[API_KEY]",
+        None,
+    ),
+    ("card", r"\b\d(?:[ -]?\d){12,18}\b", "[CARD]", Some(luhn)),
+    (
+        "phone",
+        r"(?:\+?\d{1,3}[ -]?)?(?:\(\d{2,5}\)[ -]?|\d{2,5}[ -])\d{2,4}(?:[ -]?\d{2,4}){1,3}\b",
+        "[PHONE]",
+        Some(phone),
+    ),
+];
+
+fn builtin() -> &'static [Rule] {
+    static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        BUILTIN
+            .iter()
+            .map(|(name, pattern, replacement, validate)| Rule {
+                name: name.to_string(),
+                regex: Regex::new(pattern).expect("built-in redaction pattern"),
+                replacement: replacement.to_string(),
+                validate: *validate,
+            })
+            .collect()
+    })
+}
+
+/// Whether redaction is on (`redact_enabled`, default on).
+pub fn enabled(app: &AppHandle) -> bool {
+    settings::get::<bool>(app, "redact_enabled").unwrap_or(true)
+}
+
+/// Whether redaction is on and whether screenshots can be masked, for Settings.
+#[derive(Serialize, Clone, Debug)]
+pub struct RedactionStatus {
+    pub enabled: bool,
+    /// Screenshots are masked only when an OCR engine is available;
+    /// otherwise they are sent and stored as captured.
+    pub screenshot_masking: bool,
+}
+
+pub fn status(app: &AppHandle) -> RedactionStatus {
+    let enabled = enabled(app);
+    RedactionStatus { enabled, screenshot_masking: enabled && crate::ocr::available(app) }
+}
+
+/// Active rules: built-ins not listed in `redact_disabled_rules`, the
+/// `redact_terms` dictionary and the `redact_patterns` regexes.
+fn rules(app: &AppHandle) -> Vec<Rule> {
+    let disabled: Vec<String> = settings::get(app, "redact_disabled_rules").unwrap_or_default();
+    let mut rules: Vec<Rule> = builtin()
+        .iter()
+        .filter(|r| !disabled.contains(&r.name))
+        .cloned()
+        .collect();
+
+    let terms: Vec<String> = settings::get(app, "redact_terms").unwrap_or_default();
+    let terms: Vec<String> = terms.iter().map(|t| t.trim()).filter(|t| !t.is_empty()).map(regex::escape).collect();
+    if !terms.is_empty() {
+        match Regex::new(&format!(r"(?i)\b(?:{})\b", terms.join("|"))) {
+            Ok(regex) => rules.push(Rule {
+                name: "term".to_string(),
+                regex,
+                replacement: "[REDACTED]".to_string(),
+                validate: None,
+            }),
+            Err(e) => eprintln!("[Redact] ✗ Invalid term list: {}", e),
+        }
+    }
+
+    let patterns: Vec<CustomPattern> = settings::get(app, "redact_patterns").unwrap_or_default();
+    for custom in patterns {
+        match Regex::new(&custom.pattern) {
+            Ok(regex) => rules.push(Rule {
+                replacement: format!("[{}]", custom.name.to_uppercase()),
+                name: custom.name,
+                regex,
+                validate: None,
+            }),
+            Err(e) => eprintln!("[Redact] ✗ Invalid pattern {}: {}", custom.name, e),
+        }
+    }
+    rules
+}
+
+/// Non-overlapping matches in text order; on overlap the earlier, then
+/// longer, match wins.
+fn find<'r>(rules: &'r [Rule], text: &str) -> Vec<Match<'r>> {
+    let mut matches: Vec<Match> = rules
+        .iter()
+        .flat_map(|rule| {
+            rule.regex
+                .find_iter(text)
+                .filter(|m| rule.validate.is_none_or(|validate| validate(m.as_str())))
+                .map(move |m| Match { rule, start: m.start(), end: m.end() })
+        })
+        .collect();
+    matches.sort_by_key(|m| (m.start, std::cmp::Reverse(m.end)));
+
+    let mut kept: Vec<Match> = Vec::new();
+    for m in matches {
+        if kept.last().is_none_or(|last| m.start >= last.end) {
+            kept.push(m);
+        }
+    }
+    kept
+}
+
+fn record(rule: &Rule, value: &str) -> Redaction {
+    Redaction { rule: rule.name.clone(), length: value.chars().count() }
+}
+
+fn apply(rules: &[Rule], text: &str) -> Redacted {
+    let mut out = String::with_capacity(text.len());
+    let mut redactions = Vec::new();
+    let mut cursor = 0;
+    for m in find(rules, text) {
+        out.push_str(&text[cursor..m.start]);
+        out.push_str(&m.rule.replacement);
+        redactions.push(record(m.rule, &text[m.start..m.end]));
+        cursor = m.end;
+    }
+    out.push_str(&text[cursor..]);
+    Redacted { text: out, redactions }
+}
+
+/// Redact `text` with the active rules, without logging. For previews.
+pub fn text(app: &AppHandle, text: &str) -> Redacted {
+    apply(&rules(app), text)
+}
+
+/// Redact the system prompt and text parts of a model request and log what
+/// was replaced. Borrows the request back unchanged when nothing matched.
+pub fn request<'a>(app: &AppHandle, request: &'a LlmRequest) -> Cow<'a, LlmRequest> {
+    if !enabled(app) {
+        return Cow::Borrowed(request);
+    }
+    let rules = rules(app);
+    let texts = request.system.iter().chain(request.parts.iter().filter_map(|part| match part {
+        Part::Text { text } => Some(text),
+        _ => None,
+    }));
+    // Images and audio make a clone expensive; only copy when something matched.
+    if texts.into_iter().all(|text| find(&rules, text).is_empty()) {
+        return Cow::Borrowed(request);
+    }
+
+    let mut redacted = request.clone();
+    let mut redactions = Vec::new();
+    let texts = redacted.system.iter_mut().chain(redacted.parts.iter_mut().filter_map(|part| match part {
+        Part::Text { text } => Some(text),
+        _ => None,
+    }));
+    for text in texts {
+        let result = apply(&rules, text);
+        *text = result.text;
+        redactions.extend(result.redactions);
+    }
+    log(app, "request", request.kind.as_deref(), None, &redactions);
+    Cow::Owned(redacted)
+}
+
+/// Black out the parts of `image` where OCR found text matching the active
+/// rules; `ocr` is `None` when recognition failed. Returns whether the image
+/// was masked and what was, for the caller to log with the screenshot.
+pub fn mask(app: &AppHandle, image: &mut RgbaImage, ocr: Option<&OcrResult>) -> (bool, Vec<Redaction>) {
+    mask_with(&rules(app), image, ocr)
+}
+
+fn mask_with(rules: &[Rule], image: &mut RgbaImage, ocr: Option<&OcrResult>) -> (bool, Vec<Redaction>) {
+    let Some(ocr) = ocr else {
+        eprintln!("[Redact] ✗ Screenshot not masked: OCR failed");
+        return (false, vec![Redaction { rule: "unmasked".to_string(), length: 0 }]);
+    };
+    let mut redactions = Vec::new();
+    for block in &ocr.blocks {
+        for m in find(rules, &block.text) {
+            let (left, right) = span(block, m.start, m.end);
+            let x = left.saturating_sub(MASK_PADDING);
+            let y = block.y.saturating_sub(MASK_PADDING);
+            let right = (right + MASK_PADDING).min(image.width());
+            let bottom = (block.y + block.height + MASK_PADDING).min(image.height());
+            for py in y..bottom {
+                for px in x..right {
+                    image.put_pixel(px, py, Rgba([0, 0, 0, 255]));
+                }
+            }
+            redactions.push(record(m.rule, &block.text[m.start..m.end]));
+        }
+    }
+    (true, redactions)
+}
+
+/// Horizontal extent of the words covering bytes `start..end` of the block
+/// text, or the whole block when there are no word boxes.
+fn span(block: &TextBlock, start: usize, end: usize) -> (u32, u32) {
+    let mut cursor = 0;
+    let mut extent: Option<(u32, u32)> = None;
+    for word in &block.words {
+        let Some(offset) = block.text[cursor..].find(&word.text) else { break };
+        let (word_start, word_end) = (cursor + offset, cursor + offset + word.text.len());
+        cursor = word_end;
+        if word_start < end && word_end > start {
+            let (left, right) = extent.unwrap_or((word.x, word.x + word.width));
+            extent = Some((left.min(word.x), right.max(word.x + word.width)));
+        }
+    }
+    extent.unwrap_or((block.x, block.x + block.width))
+}
+
+/// Append redactions to the audit log in the session store.
+pub fn log(app: &AppHandle, target: &str, kind: Option<&str>, ref_id: Option<i64>, redactions: &[Redaction]) {
+    if redactions.is_empty() {
+        return;
+    }
+    println!("[Redact] {} redactions in {}", redactions.len(), target);
+    let Some(sessions) = app.try_state::<SessionStore>() else { return };
+    if let Err(e) = sessions.add_redactions(target, kind, ref_id, redactions) {
+        eprintln!("[Redact] ✗ {}", e);
+    }
+}
+
+/// Luhn checksum, so arbitrary digit runs aren't taken for card numbers.
+fn luhn(value: &str) -> bool {
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            let d = if i % 2 == 1 { d * 2 } else { d };
+            if d > 9 { d - 9 } else { d }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Phone numbers have 10 to 15 digits; fewer is more likely a date or a count.
+fn phone(value: &str) -> bool {
+    (10..=15).contains(&value.chars().filter(char::is_ascii_digit).count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::Word;
+
+    fn matched(text: &str) -> Vec<&'static str> {
+        find(builtin(), text).iter().map(|m| m.rule.name.as_str()).collect()
+    }
+
+    #[test]
+    fn luhn_accepts_only_valid_card_numbers() {
+        assert!(luhn("4111 1111 1111 1111"));
+        assert!(luhn("5500-0000-0000-0004"));
+        assert!(!luhn("4111 1111 1111 1112"));
+    }
+
+    #[test]
+    fn phone_needs_ten_to_fifteen_digits() {
+        assert!(phone("+7 916 123-45-67"));
+        assert!(phone("(555) 123-4567"));
+        assert!(!phone("2024-01-15"));
+        assert!(!phone("+1 234 567 890 123 456"));
+    }
+
+    #[test]
+    fn overlapping_matches_keep_the_earlier_longer_one() {
+        // The phone rule matches the local part, the email rule all of it.
+        assert_eq!(matched("mail 5551234567@example.com"), ["email"]);
+        assert_eq!(matched("card 4111 1111 1111 1111"), ["card"]);
+        assert_eq!(matched("a@example.com, +7 916 123-45-67"), ["email", "phone"]);
+    }
+
+    #[test]
+    fn dates_versions_and_addresses_are_left_alone() {
+        assert!(matched("2024-01-15 v1.2.3 10.0.0.1 port 8080, 3 of 12").is_empty());
+    }
+
+    #[test]
+    fn apply_replaces_and_records_lengths() {
+        let result = apply(builtin(), "write to a.b@example.com or +7 916 123-45-67");
+        assert_eq!(result.text, "write to [EMAIL] or [PHONE]");
+        let lengths: Vec<usize> = result.redactions.iter().map(|r| r.length).collect();
+        assert_eq!(lengths, [15, 16]);
+    }
+
+    fn block(text: &str, words: &[(&str, u32, u32)]) -> TextBlock {
+        TextBlock {
+            text: text.to_string(),
+            x: 10,
+            y: 0,
+            width: 300,
+            height: 20,
+            confidence: 1.0,
+            words: words.iter().map(|&(text, x, width)| Word { text: text.to_string(), x, width }).collect(),
+        }
+    }
+
+    #[test]
+    fn span_covers_the_matched_words() {
+        let block = block("Email: a@b.co now", &[("Email:", 10, 60), ("a@b.co", 80, 70), ("now", 160, 40)]);
+        assert_eq!(span(&block, 7, 13), (80, 150));
+        // A match across words covers both.
+        assert_eq!(span(&block, 7, 17), (80, 200));
+    }
+
+    #[test]
+    fn span_falls_back_to_the_block_without_words() {
+        assert_eq!(span(&block("Email: a@b.co", &[]), 7, 13), (10, 310));
+    }
+
+    #[test]
+    fn mask_blacks_out_matches_in_the_image() {
+        let mut image = RgbaImage::from_pixel(320, 40, Rgba([255, 255, 255, 255]));
+        let ocr = OcrResult {
+            text: "Email: a@b.co now".to_string(),
+            blocks: vec![block("Email: a@b.co now", &[("Email:", 10, 60), ("a@b.co", 80, 70), ("now", 160, 40)])],
+            code: Vec::new(),
+            width: 320,
+            height: 40,
+            elapsed_ms: 0,
+        };
+        let (masked, redactions) = mask_with(builtin(), &mut image, Some(&ocr));
+        assert!(masked);
+        assert_eq!(redactions.len(), 1);
+        assert_eq!(redactions[0].rule, "email");
+        assert_eq!(image.get_pixel(100, 10), &Rgba([0, 0, 0, 255]));
+        assert_eq!(image.get_pixel(30, 10), &Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn failed_ocr_leaves_the_image_and_records_it_unmasked() {
+        let mut image = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255]));
+        let (masked, redactions) = mask_with(builtin(), &mut image, None);
+        assert!(!masked);
+        assert_eq!(redactions.len(), 1);
+        assert_eq!((redactions[0].rule.as_str(), redactions[0].length), ("unmasked", 0));
+        assert!(image.pixels().all(|p| p == &Rgba([255, 255, 255, 255])));
+    }
+}
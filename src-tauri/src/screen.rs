@@ -0,0 +1,587 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbaImage};
+use screenshots::Screen;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::ocr::{self, OcrResult};
+use crate::redact;
+use crate::sessions::{NewScreenshot, SessionStore};
+use crate::settings;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DisplayInfo {
+    pub id: u32,
+    /// Position and size in the OS desktop coordinate space.
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub rotation: f32,
+    /// The primary display is the one at the desktop origin.
+    pub is_primary: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayMode {
+    Primary,
+    /// The display under the mouse cursor.
+    Cursor,
+    /// All displays stitched into one image by their desktop layout.
+    All,
+}
+
+/// A rectangle in physical pixels of the captured image.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct WindowInfo {
+    /// Native window handle; pass it back to `capture_screenshot`.
+    pub id: i64,
+    pub title: String,
+    /// Outer bounds in physical desktop pixels.
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub minimized: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+    /// Lossless; `quality` does not apply.
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+}
+
+/// Per-capture overrides for the `screenshot_*` encoding settings.
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+pub struct EncodeOptions {
+    /// Longest side in pixels; larger captures are scaled down.
+    pub max_dimension: Option<u32>,
+    pub format: Option<ImageFormat>,
+    /// JPEG quality, 1–100.
+    pub quality: Option<u8>,
+    pub grayscale: Option<bool>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Screenshot {
+    /// Base64 of the encoded image.
+    pub data: String,
+    pub format: ImageFormat,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    /// Size of the encoded image.
+    pub bytes: usize,
+    /// Size of the capture before scaling.
+    pub source_width: u32,
+    pub source_height: u32,
+    /// Row in the active session, if one is recording.
+    pub id: Option<i64>,
+    /// Local OCR of the full-resolution capture, when enabled.
+    pub ocr: Option<OcrResult>,
+    /// Send `ocr.text` instead of the image (see `screenshot_send`).
+    pub prefer_text: bool,
+    /// Whether the image was checked for text to black out: `None` with
+    /// redaction off or no OCR engine (see `get_redaction_status`), `false`
+    /// when recognition failed on this capture.
+    pub masked: Option<bool>,
+}
+
+/// An encoded capture.
+pub struct Encoded {
+    pub bytes: Vec<u8>,
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Where a capture comes from: the window `window`, or else a display,
+/// cropped to `region`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaptureSource {
+    pub display: Option<DisplaySelector>,
+    pub window: Option<i64>,
+    pub region: Option<Region>,
+}
+
+const DEFAULT_JPEG_QUALITY: u8 = 85;
+/// Longest side of stored thumbnails.
+const THUMBNAIL_SIZE: u32 = 320;
+const THUMBNAIL_QUALITY: u8 = 80;
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Which display to capture: an ID from `list_displays`, or a mode.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(untagged)]
+pub enum DisplaySelector {
+    Id(u32),
+    Mode(DisplayMode),
+}
+
+pub fn list_displays() -> Vec<DisplayInfo> {
+    Screen::all().iter().map(display_info).collect()
+}
+
+fn display_info(screen: &Screen) -> DisplayInfo {
+    DisplayInfo {
+        id: screen.id,
+        x: screen.x,
+        y: screen.y,
+        width: screen.width,
+        height: screen.height,
+        scale_factor: screen.scale,
+        rotation: screen.rotation,
+        is_primary: screen.x == 0 && screen.y == 0,
+    }
+}
+
+/// Capture the selected display. Without a selector, the `screenshot_display`
+/// setting is used, then the primary display.
+pub fn capture(app: &AppHandle, selector: Option<DisplaySelector>) -> Result<RgbaImage, String> {
+    let selector = resolve_display(app, selector);
+    let screens = Screen::all();
+    if screens.is_empty() {
+        return Err("No screens found".to_string());
+    }
+
+    let screen = match selector {
+        DisplaySelector::Id(id) => {
+            screens.iter().find(|s| s.id == id).ok_or_else(|| format!("Display {} not found", id))?
+        }
+        DisplaySelector::Mode(DisplayMode::Primary) => primary(&screens),
+        DisplaySelector::Mode(DisplayMode::Cursor) => match app.cursor_position() {
+            Ok(pos) => screens.iter().find(|s| contains(s, pos.x, pos.y)).unwrap_or_else(|| primary(&screens)),
+            Err(e) => {
+                eprintln!("[Screenshot] Cursor position unavailable, using the primary display: {}", e);
+                primary(&screens)
+            }
+        },
+        DisplaySelector::Mode(DisplayMode::All) => return stitch(&screens),
+    };
+    capture_screen(screen)
+}
+
+fn resolve_display(app: &AppHandle, selector: Option<DisplaySelector>) -> DisplaySelector {
+    selector
+        .or_else(|| settings::get(app, "screenshot_display"))
+        .unwrap_or(DisplaySelector::Mode(DisplayMode::Primary))
+}
+
+fn primary(screens: &[Screen]) -> &Screen {
+    screens.iter().find(|s| s.x == 0 && s.y == 0).unwrap_or(&screens[0])
+}
+
+/// Whether a physical cursor position lies on `screen`. macOS reports display
+/// geometry in points, everything else in physical pixels.
+fn contains(screen: &Screen, x: f64, y: f64) -> bool {
+    let scale = if cfg!(target_os = "macos") { screen.scale as f64 } else { 1.0 };
+    let (left, top) = (screen.x as f64 * scale, screen.y as f64 * scale);
+    let (right, bottom) = (left + screen.width as f64 * scale, top + screen.height as f64 * scale);
+    x >= left && x < right && y >= top && y < bottom
+}
+
+fn capture_screen(screen: &Screen) -> Result<RgbaImage, String> {
+    let image = screen.capture().ok_or_else(|| format!("Failed to capture display {}", screen.id))?;
+    decode(image)
+}
+
+/// `screenshots` hands back PNG on every platform; raw RGBA of the reported
+/// size is accepted too. Anything else is an error rather than a guess.
+fn decode(image: screenshots::Image) -> Result<RgbaImage, String> {
+    let (width, height) = (image.width(), image.height());
+    let buffer = image.buffer();
+    if buffer.starts_with(PNG_SIGNATURE) {
+        let decoded = image::load_from_memory_with_format(buffer, image::ImageFormat::Png)
+            .map_err(|e| format!("Failed to decode screenshot: {}", e))?;
+        return Ok(decoded.to_rgba8());
+    }
+    if buffer.len() as u64 == width as u64 * height as u64 * 4 {
+        return RgbaImage::from_raw(width, height, buffer.clone()).ok_or_else(|| "Invalid screenshot buffer".to_string());
+    }
+    Err(format!(
+        "Unexpected screenshot buffer: {} bytes for {}×{}, neither PNG nor RGBA",
+        buffer.len(),
+        width,
+        height
+    ))
+}
+
+/// Lay captures out as the displays are arranged. Offsets are scaled by the
+/// largest capture-to-layout ratio, so mixed-DPI setups line up approximately.
+fn stitch(screens: &[Screen]) -> Result<RgbaImage, String> {
+    let captures: Vec<(&Screen, RgbaImage)> =
+        screens.iter().map(|s| capture_screen(s).map(|img| (s, img))).collect::<Result<_, _>>()?;
+    let ratio = captures
+        .iter()
+        .map(|(s, img)| img.width() as f64 / s.width.max(1) as f64)
+        .fold(1.0, f64::max);
+    let min_x = screens.iter().map(|s| s.x).min().unwrap_or(0);
+    let min_y = screens.iter().map(|s| s.y).min().unwrap_or(0);
+
+    let placed: Vec<(i64, i64, &RgbaImage)> = captures
+        .iter()
+        .map(|(s, img)| (((s.x - min_x) as f64 * ratio) as i64, ((s.y - min_y) as f64 * ratio) as i64, img))
+        .collect();
+    let width = placed.iter().map(|(x, _, img)| *x as u32 + img.width()).max().unwrap_or(0);
+    let height = placed.iter().map(|(_, y, img)| *y as u32 + img.height()).max().unwrap_or(0);
+
+    let mut canvas = RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 255]));
+    for (x, y, img) in placed {
+        imageops::replace(&mut canvas, img, x, y);
+    }
+    Ok(canvas)
+}
+
+/// Cut `region` out of `image`. A region reaching past the edge is clipped.
+pub fn crop(image: &RgbaImage, region: Region) -> Result<RgbaImage, String> {
+    let (width, height) = image.dimensions();
+    if region.x >= width || region.y >= height {
+        return Err(format!(
+            "Region starts at {},{} outside the {}×{} capture",
+            region.x, region.y, width, height
+        ));
+    }
+    let w = region.width.min(width - region.x);
+    let h = region.height.min(height - region.y);
+    if w == 0 || h == 0 {
+        return Err("Region is empty".to_string());
+    }
+    Ok(imageops::crop_imm(image, region.x, region.y, w, h).to_image())
+}
+
+/// Visible top-level windows of other apps, in Z order.
+pub fn list_windows() -> Result<Vec<WindowInfo>, String> {
+    #[cfg(target_os = "windows")]
+    {
+        Ok(win::list_windows())
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        Err("Window capture is only supported on Windows".to_string())
+    }
+}
+
+/// Capture one top-level window, even when it is covered by others.
+pub fn capture_window(id: i64) -> Result<RgbaImage, String> {
+    #[cfg(target_os = "windows")]
+    {
+        win::capture_window(id)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        Err(format!("Cannot capture window {}: window capture is only supported on Windows", id))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod win {
+    use super::WindowInfo;
+    use image::RgbaImage;
+    use std::mem::{size_of, zeroed};
+    use std::ptr::null_mut;
+    use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, TRUE};
+    use winapi::shared::windef::{HWND, RECT};
+    use winapi::um::dwmapi::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+    use winapi::um::wingdi::{
+        CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDIBits, SelectObject, BITMAPINFO,
+        BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+    };
+    use winapi::um::winuser::{
+        EnumWindows, GetDC, GetWindow, GetWindowLongW, GetWindowRect, GetWindowTextLengthW, GetWindowTextW,
+        GetWindowThreadProcessId, IsIconic, IsWindow, IsWindowVisible, PrintWindow, ReleaseDC, GWL_EXSTYLE, GW_OWNER,
+        PW_RENDERFULLCONTENT, WS_EX_TOOLWINDOW,
+    };
+
+    pub fn list_windows() -> Vec<WindowInfo> {
+        unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            (*(lparam as *mut Vec<HWND>)).push(hwnd);
+            TRUE
+        }
+        let mut handles: Vec<HWND> = Vec::new();
+        unsafe { EnumWindows(Some(collect), &mut handles as *mut Vec<HWND> as LPARAM) };
+        handles.into_iter().filter_map(|hwnd| unsafe { describe(hwnd) }).collect()
+    }
+
+    /// Windows a user would alt-tab to: visible, titled, not owned, not tool
+    /// windows, not cloaked and not our own.
+    unsafe fn describe(hwnd: HWND) -> Option<WindowInfo> {
+        if IsWindowVisible(hwnd) == 0 || !GetWindow(hwnd, GW_OWNER).is_null() {
+            return None;
+        }
+        if GetWindowLongW(hwnd, GWL_EXSTYLE) as DWORD & WS_EX_TOOLWINDOW != 0 {
+            return None;
+        }
+        let mut cloaked: DWORD = 0;
+        let size = size_of::<DWORD>() as DWORD;
+        if DwmGetWindowAttribute(hwnd, DWMWA_CLOAKED, &mut cloaked as *mut DWORD as _, size) == 0 && cloaked != 0 {
+            return None;
+        }
+        let mut pid: DWORD = 0;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        if pid == std::process::id() {
+            return None;
+        }
+
+        let len = GetWindowTextLengthW(hwnd);
+        if len <= 0 {
+            return None;
+        }
+        let mut buffer = vec![0u16; len as usize + 1];
+        let copied = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32).max(0) as usize;
+        let mut rect: RECT = zeroed();
+        GetWindowRect(hwnd, &mut rect);
+        Some(WindowInfo {
+            id: hwnd as isize as i64,
+            title: String::from_utf16_lossy(&buffer[..copied]),
+            x: rect.left,
+            y: rect.top,
+            width: (rect.right - rect.left).max(0) as u32,
+            height: (rect.bottom - rect.top).max(0) as u32,
+            minimized: IsIconic(hwnd) != 0,
+        })
+    }
+
+    pub fn capture_window(id: i64) -> Result<RgbaImage, String> {
+        let hwnd = id as isize as HWND;
+        unsafe {
+            if IsWindow(hwnd) == 0 {
+                return Err(format!("Window {} not found", id));
+            }
+            if IsIconic(hwnd) != 0 {
+                return Err("Cannot capture a minimized window".to_string());
+            }
+            let mut rect: RECT = zeroed();
+            GetWindowRect(hwnd, &mut rect);
+            let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+            if width <= 0 || height <= 0 {
+                return Err("The window has no visible area".to_string());
+            }
+
+            let screen_dc = GetDC(null_mut());
+            let mem_dc = CreateCompatibleDC(screen_dc);
+            let bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+            let previous = SelectObject(mem_dc, bitmap as _);
+            // PW_RENDERFULLCONTENT also captures DirectComposition content (browsers, UWP).
+            let printed = PrintWindow(hwnd, mem_dc, PW_RENDERFULLCONTENT);
+
+            let mut info: BITMAPINFO = zeroed();
+            info.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as DWORD;
+            info.bmiHeader.biWidth = width;
+            info.bmiHeader.biHeight = -height; // top-down rows
+            info.bmiHeader.biPlanes = 1;
+            info.bmiHeader.biBitCount = 32;
+            info.bmiHeader.biCompression = BI_RGB;
+            let mut pixels = vec![0u8; (width * height * 4) as usize];
+            let lines =
+                GetDIBits(mem_dc, bitmap, 0, height as u32, pixels.as_mut_ptr() as _, &mut info, DIB_RGB_COLORS);
+
+            SelectObject(mem_dc, previous);
+            DeleteObject(bitmap as _);
+            DeleteDC(mem_dc);
+            ReleaseDC(null_mut(), screen_dc);
+
+            if printed == 0 || lines == 0 {
+                return Err(format!("Failed to capture window {}", id));
+            }
+            // BGRA → RGBA; PrintWindow leaves alpha undefined.
+            for px in pixels.chunks_exact_mut(4) {
+                px.swap(0, 2);
+                px[3] = 255;
+            }
+            RgbaImage::from_raw(width as u32, height as u32, pixels).ok_or_else(|| "Invalid window bitmap".to_string())
+        }
+    }
+}
+
+pub fn grab(app: &AppHandle, source: &CaptureSource) -> Result<RgbaImage, String> {
+    let image = match source.window {
+        Some(id) => capture_window(id)?,
+        None => capture(app, source.display)?,
+    };
+    match source.region {
+        Some(region) => crop(&image, region),
+        None => Ok(image),
+    }
+}
+
+/// OCR (per `ocr`, defaulting to the `screenshot_ocr` setting), mask, encode
+/// and store a capture with a thumbnail in the active session. `source` is
+/// `None` for images that didn't come from the screen, such as the clipboard.
+/// Blocks during OCR; a failed OCR is logged and doesn't fail the capture.
+pub fn process(
+    app: &AppHandle,
+    mut image: RgbaImage,
+    source: Option<&CaptureSource>,
+    encoding: EncodeOptions,
+    ocr: Option<bool>,
+) -> Result<Screenshot, String> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let (source_width, source_height) = image.dimensions();
+    let wanted = ocr.or_else(|| settings::get(app, "screenshot_ocr")).unwrap_or(false);
+    // Masking needs the OCR boxes, so it runs OCR whatever `screenshot_ocr`
+    // says. Without an engine it is skipped; Settings shows that once.
+    let redacting = redact::enabled(app);
+    let masking = redacting && ocr::available(app);
+    // OCR sees the full-resolution capture.
+    let recognized = if wanted || masking {
+        ocr::recognize(app, &image).map_err(|e| eprintln!("[OCR] ✗ {}", e)).ok()
+    } else {
+        None
+    };
+    let (masked, redactions) = if masking {
+        let (masked, redactions) = redact::mask(app, &mut image, recognized.as_ref());
+        (Some(masked), redactions)
+    } else {
+        (None, Vec::new())
+    };
+    let ocr_result = recognized.filter(|_| wanted);
+
+    let thumbnail = thumbnail(&image).map_err(|e| eprintln!("[Screenshot] ✗ {}", e)).ok();
+    let encoded = encode(app, image, encoding)?;
+
+    let sessions = app.state::<SessionStore>();
+    let display = match source {
+        Some(source) if source.window.is_none() => serde_json::to_value(resolve_display(app, source.display)).ok(),
+        _ => None,
+    };
+    let id = sessions
+        .add_screenshot(&NewScreenshot {
+            image: &encoded.bytes,
+            extension: encoded.format.extension(),
+            mime_type: encoded.format.mime_type(),
+            width: encoded.width,
+            height: encoded.height,
+            thumbnail: thumbnail.as_deref(),
+            source_width,
+            source_height,
+            display,
+            window: source.and_then(|s| s.window),
+            region: source.and_then(|s| s.region).and_then(|r| serde_json::to_value(r).ok()),
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("[Screenshot] ✗ {}", e);
+            None
+        });
+    redact::log(app, "screenshot", None, id, &redactions);
+    if let (Some(id), Some(result)) = (id, &ocr_result) {
+        // Stored and indexed text gets the same rules as what is sent.
+        let text = if redacting { redact::text(app, &result.text).text } else { result.text.clone() };
+        if let Err(e) = sessions.set_screenshot_text(id, &text) {
+            eprintln!("[Screenshot] ✗ {}", e);
+        }
+    }
+    let prefer_text = ocr_result
+        .as_ref()
+        .is_some_and(|r| ocr::prefer_text(app, &r.text, encoded.width, encoded.height));
+    println!(
+        "[Screenshot] ✓ {}×{} → {}×{} {:?}, {} KB",
+        source_width, source_height, encoded.width, encoded.height, encoded.format, encoded.bytes.len() / 1024
+    );
+
+    Ok(Screenshot {
+        data: general_purpose::STANDARD.encode(&encoded.bytes),
+        format: encoded.format,
+        mime_type: encoded.format.mime_type().to_string(),
+        width: encoded.width,
+        height: encoded.height,
+        bytes: encoded.bytes.len(),
+        source_width,
+        source_height,
+        id,
+        ocr: ocr_result,
+        prefer_text,
+        masked,
+    })
+}
+
+/// A small JPEG preview for screenshot history.
+fn thumbnail(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let (width, height) = image.dimensions();
+    let scale = (THUMBNAIL_SIZE as f32 / width.max(height) as f32).min(1.0);
+    let (thumb_width, thumb_height) = (
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+    );
+    let thumb = DynamicImage::ImageRgba8(imageops::thumbnail(image, thumb_width, thumb_height)).to_rgb8();
+    let mut bytes = Vec::new();
+    thumb
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, THUMBNAIL_QUALITY))
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+    Ok(bytes)
+}
+
+/// Scale down, optionally convert to grayscale and encode, following the
+/// `screenshot_max_dimension`, `screenshot_format`, `screenshot_quality` and
+/// `screenshot_grayscale` settings unless `options` overrides them.
+pub fn encode(app: &AppHandle, image: RgbaImage, options: EncodeOptions) -> Result<Encoded, String> {
+    let max_dimension = options
+        .max_dimension
+        .or_else(|| settings::get(app, "screenshot_max_dimension"))
+        .filter(|d| *d > 0);
+    let format = options.format.or_else(|| settings::get(app, "screenshot_format")).unwrap_or_default();
+    let quality = options
+        .quality
+        .or_else(|| settings::get(app, "screenshot_quality"))
+        .unwrap_or(DEFAULT_JPEG_QUALITY)
+        .clamp(1, 100);
+    let grayscale = options.grayscale.or_else(|| settings::get(app, "screenshot_grayscale")).unwrap_or(false);
+
+    let mut image = DynamicImage::ImageRgba8(image);
+    if let Some(max) = max_dimension {
+        if image.width() > max || image.height() > max {
+            // Catmull-Rom keeps small text readable.
+            image = image.resize(max, max, FilterType::CatmullRom);
+        }
+    }
+    // Screens are opaque; dropping alpha makes every format smaller.
+    image = if grayscale {
+        DynamicImage::ImageLuma8(image.to_luma8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    };
+
+    let mut bytes = Vec::new();
+    let result = match format {
+        ImageFormat::Png => image.write_with_encoder(PngEncoder::new(&mut bytes)),
+        ImageFormat::Jpeg => image.write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality)),
+        ImageFormat::Webp => image.write_with_encoder(WebPEncoder::new_lossless(&mut bytes)),
+    };
+    result.map_err(|e| format!("Failed to encode {:?}: {}", format, e))?;
+    Ok(Encoded { bytes, format, width: image.width(), height: image.height() })
+}
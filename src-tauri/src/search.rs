@@ -0,0 +1,177 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+/// Markers FTS5 puts around matched terms in snippets; replaced with `<mark>`
+/// after the rest of the snippet has been HTML-escaped.
+const MATCH_OPEN: &str = "\u{2}";
+const MATCH_CLOSE: &str = "\u{3}";
+
+const DEFAULT_LIMIT: i64 = 50;
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SearchQuery {
+    /// Free text; every word must match, the last one as a prefix.
+    pub text: String,
+    pub speaker: Option<String>,
+    pub session_id: Option<i64>,
+    /// Unix milliseconds, inclusive.
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchHit {
+    /// `segment`, `exchange` or `screenshot`.
+    pub kind: String,
+    /// Row ID in the table named by `kind`.
+    pub ref_id: i64,
+    pub session_id: i64,
+    pub session_title: String,
+    pub speaker: Option<String>,
+    /// Wall-clock time of the hit (Unix ms) and its offset from the session start.
+    pub at_ms: i64,
+    pub offset_ms: i64,
+    /// HTML-escaped excerpt with matches wrapped in `<mark>…</mark>`.
+    pub snippet: String,
+    /// BM25 score; lower is more relevant.
+    pub rank: f64,
+}
+
+/// Run a ranked full-text search over the session index.
+pub fn run(conn: &Connection, query: &SearchQuery) -> Result<Vec<SearchHit>, String> {
+    let Some(fts_query) = to_fts_query(&query.text) else { return Ok(Vec::new()) };
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, 500);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT search_index.kind, search_index.ref_id, search_index.session_id, s.title,
+                    search_index.speaker, search_index.at_ms, search_index.at_ms - s.started_at,
+                    snippet(search_index, 0, ?2, ?3, '…', 16),
+                    bm25(search_index)
+             FROM search_index
+             JOIN sessions s ON s.id = search_index.session_id
+             WHERE search_index MATCH ?1
+               AND (?4 IS NULL OR search_index.speaker = ?4)
+               AND (?5 IS NULL OR search_index.session_id = ?5)
+               AND (?6 IS NULL OR search_index.at_ms >= ?6)
+               AND (?7 IS NULL OR search_index.at_ms <= ?7)
+             ORDER BY bm25(search_index)
+             LIMIT ?8",
+        )
+        .map_err(|e| format!("Failed to prepare search: {}", e))?;
+
+    let rows = stmt
+        .query_map(
+            params![
+                fts_query,
+                MATCH_OPEN,
+                MATCH_CLOSE,
+                query.speaker,
+                query.session_id,
+                query.from,
+                query.to,
+                limit
+            ],
+            |r| {
+                let raw_snippet: String = r.get(7)?;
+                Ok(SearchHit {
+                    kind: r.get(0)?,
+                    ref_id: r.get(1)?,
+                    session_id: r.get(2)?,
+                    session_title: r.get(3)?,
+                    speaker: r.get(4)?,
+                    at_ms: r.get(5)?,
+                    offset_ms: r.get::<_, i64>(6)?.max(0),
+                    snippet: highlight(&raw_snippet),
+                    rank: r.get(8)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Search failed: {}", e))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read search result: {}", e))
+}
+
+/// Turn free text into a safe FTS5 query: each word becomes a quoted phrase
+/// (so punctuation can't be parsed as query syntax), all words must match and
+/// the last one also matches as a prefix for search-as-you-type.
+fn to_fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+        .collect();
+    let (last, rest) = terms.split_last()?;
+    let mut query = rest.join(" ");
+    if !query.is_empty() {
+        query.push(' ');
+    }
+    query.push_str(last);
+    query.push('*');
+    Some(query)
+}
+
+fn highlight(raw: &str) -> String {
+    raw.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace(MATCH_OPEN, "<mark>")
+        .replace(MATCH_CLOSE, "</mark>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::sessions::migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO sessions (id, title, started_at) VALUES (1, 'System design', 1000);
+             INSERT INTO segments (session_id, speaker, text, start_ms, end_ms) VALUES
+                 (1, 'interviewer', 'How would you design a caching layer?', 0, 2000),
+                 (1, 'me', 'I would put a <cache> in front of the database & shard it.', 2000, 5000),
+                 (1, 'interviewer', 'What about C++ templates?', 9000, 10000);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn search(conn: &Connection, text: &str, speaker: Option<&str>) -> Vec<SearchHit> {
+        let query = SearchQuery { text: text.to_string(), speaker: speaker.map(str::to_string), ..Default::default() };
+        run(conn, &query).unwrap()
+    }
+
+    #[test]
+    fn words_are_quoted_and_the_last_is_a_prefix() {
+        assert_eq!(to_fts_query("design cach").as_deref(), Some("\"design\" \"cach\"*"));
+        assert_eq!(to_fts_query("say \"hi\"").as_deref(), Some("\"say\" \"\"\"hi\"\"\"*"));
+        assert_eq!(to_fts_query("   "), None);
+    }
+
+    #[test]
+    fn snippets_are_escaped_before_marking() {
+        let raw = format!("a <b> & {}c{}", MATCH_OPEN, MATCH_CLOSE);
+        assert_eq!(highlight(&raw), "a &lt;b&gt; &amp; <mark>c</mark>");
+    }
+
+    #[test]
+    fn search_matches_prefixes_and_filters_by_speaker() {
+        let conn = index();
+        let hits = search(&conn, "cach", None);
+        assert_eq!(hits.len(), 2);
+        let hits = search(&conn, "cach", Some("me"));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].offset_ms, 2000);
+        assert!(hits[0].snippet.contains("&lt;<mark>cache</mark>&gt;"));
+    }
+
+    #[test]
+    fn query_syntax_in_the_text_is_searched_literally() {
+        let conn = index();
+        assert_eq!(search(&conn, "C++ (templates", None).len(), 1);
+        assert!(search(&conn, "NOT OR", None).is_empty());
+    }
+}
@@ -0,0 +1,971 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how many
+/// have run, so only append to this list — never edit an entry that shipped.
+const MIGRATIONS: &[&str] = &[
+    // 1: sessions with transcript segments, assistant exchanges and screenshots
+    r#"
+    CREATE TABLE sessions (
+        id          INTEGER PRIMARY KEY,
+        title       TEXT    NOT NULL,
+        started_at  INTEGER NOT NULL,
+        ended_at    INTEGER,
+        devices     TEXT    NOT NULL DEFAULT '[]',
+        settings    TEXT    NOT NULL DEFAULT '{}'
+    );
+    CREATE TABLE segments (
+        id          INTEGER PRIMARY KEY,
+        session_id  INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        speaker     TEXT    NOT NULL,
+        text        TEXT    NOT NULL,
+        start_ms    INTEGER NOT NULL,
+        end_ms      INTEGER NOT NULL,
+        confidence  REAL
+    );
+    CREATE INDEX segments_by_session ON segments(session_id, start_ms);
+    CREATE TABLE exchanges (
+        id          INTEGER PRIMARY KEY,
+        session_id  INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        created_at  INTEGER NOT NULL,
+        kind        TEXT    NOT NULL,
+        model       TEXT,
+        prompt      TEXT    NOT NULL,
+        response    TEXT    NOT NULL
+    );
+    CREATE INDEX exchanges_by_session ON exchanges(session_id, created_at);
+    CREATE TABLE screenshots (
+        id          INTEGER PRIMARY KEY,
+        session_id  INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        captured_at INTEGER NOT NULL,
+        path        TEXT    NOT NULL,
+        width       INTEGER NOT NULL,
+        height      INTEGER NOT NULL
+    );
+    CREATE INDEX screenshots_by_session ON screenshots(session_id, captured_at);
+    "#,
+    // 2: full-text index over transcript segments, exchanges and screenshot text
+    r#"
+    ALTER TABLE screenshots ADD COLUMN ocr_text TEXT;
+
+    CREATE VIRTUAL TABLE search_index USING fts5(
+        body,
+        kind       UNINDEXED,
+        ref_id     UNINDEXED,
+        session_id UNINDEXED,
+        speaker    UNINDEXED,
+        at_ms      UNINDEXED,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+
+    CREATE TRIGGER segments_ai AFTER INSERT ON segments BEGIN
+        INSERT INTO search_index (body, kind, ref_id, session_id, speaker, at_ms)
+        VALUES (NEW.text, 'segment', NEW.id, NEW.session_id, NEW.speaker,
+                (SELECT started_at FROM sessions WHERE id = NEW.session_id) + NEW.start_ms);
+    END;
+    CREATE TRIGGER segments_ad AFTER DELETE ON segments BEGIN
+        DELETE FROM search_index WHERE kind = 'segment' AND ref_id = OLD.id;
+    END;
+
+    CREATE TRIGGER exchanges_ai AFTER INSERT ON exchanges BEGIN
+        INSERT INTO search_index (body, kind, ref_id, session_id, speaker, at_ms)
+        VALUES (NEW.prompt || char(10) || NEW.response, 'exchange', NEW.id, NEW.session_id, 'assistant', NEW.created_at);
+    END;
+    CREATE TRIGGER exchanges_ad AFTER DELETE ON exchanges BEGIN
+        DELETE FROM search_index WHERE kind = 'exchange' AND ref_id = OLD.id;
+    END;
+
+    CREATE TRIGGER screenshots_ai AFTER INSERT ON screenshots WHEN NEW.ocr_text IS NOT NULL BEGIN
+        INSERT INTO search_index (body, kind, ref_id, session_id, speaker, at_ms)
+        VALUES (NEW.ocr_text, 'screenshot', NEW.id, NEW.session_id, NULL, NEW.captured_at);
+    END;
+    CREATE TRIGGER screenshots_au AFTER UPDATE OF ocr_text ON screenshots BEGIN
+        DELETE FROM search_index WHERE kind = 'screenshot' AND ref_id = OLD.id;
+        INSERT INTO search_index (body, kind, ref_id, session_id, speaker, at_ms)
+        SELECT NEW.ocr_text, 'screenshot', NEW.id, NEW.session_id, NULL, NEW.captured_at
+        WHERE NEW.ocr_text IS NOT NULL;
+    END;
+    CREATE TRIGGER screenshots_ad AFTER DELETE ON screenshots BEGIN
+        DELETE FROM search_index WHERE kind = 'screenshot' AND ref_id = OLD.id;
+    END;
+
+    INSERT INTO search_index (body, kind, ref_id, session_id, speaker, at_ms)
+        SELECT g.text, 'segment', g.id, g.session_id, g.speaker, s.started_at + g.start_ms
+        FROM segments g JOIN sessions s ON s.id = g.session_id;
+    INSERT INTO search_index (body, kind, ref_id, session_id, speaker, at_ms)
+        SELECT prompt || char(10) || response, 'exchange', id, session_id, 'assistant', created_at
+        FROM exchanges;
+    "#,
+    // 3: token usage and cost per model request; kept when a session is deleted
+    r#"
+    CREATE TABLE usage (
+        id            INTEGER PRIMARY KEY,
+        session_id    INTEGER REFERENCES sessions(id) ON DELETE SET NULL,
+        created_at    INTEGER NOT NULL,
+        day           TEXT    NOT NULL,
+        provider      TEXT    NOT NULL,
+        model         TEXT    NOT NULL,
+        kind          TEXT,
+        input_tokens  INTEGER NOT NULL,
+        output_tokens INTEGER NOT NULL,
+        audio_seconds REAL    NOT NULL DEFAULT 0,
+        images        INTEGER NOT NULL DEFAULT 0,
+        cost          REAL
+    );
+    CREATE INDEX usage_by_session ON usage(session_id);
+    CREATE INDEX usage_by_day ON usage(day);
+    "#,
+    // 4: screenshot history: thumbnails and capture metadata
+    r#"
+    ALTER TABLE screenshots ADD COLUMN thumb_path    TEXT;
+    ALTER TABLE screenshots ADD COLUMN mime_type     TEXT;
+    ALTER TABLE screenshots ADD COLUMN bytes         INTEGER;
+    ALTER TABLE screenshots ADD COLUMN source_width  INTEGER;
+    ALTER TABLE screenshots ADD COLUMN source_height INTEGER;
+    ALTER TABLE screenshots ADD COLUMN display       TEXT;
+    ALTER TABLE screenshots ADD COLUMN window        INTEGER;
+    ALTER TABLE screenshots ADD COLUMN region        TEXT;
+    CREATE INDEX screenshots_by_time ON screenshots(captured_at);
+    "#,
+    // 5: audit log of redacted values; kept when a session is deleted
+    r#"
+    CREATE TABLE redactions (
+        id          INTEGER PRIMARY KEY,
+        session_id  INTEGER REFERENCES sessions(id) ON DELETE SET NULL,
+        created_at  INTEGER NOT NULL,
+        target      TEXT    NOT NULL,
+        kind        TEXT,
+        ref_id      INTEGER,
+        rule        TEXT    NOT NULL,
+        length      INTEGER NOT NULL
+    );
+    CREATE INDEX redactions_by_time ON redactions(created_at);
+    "#,
+];
+
+/// Session row as shown in the session list. Times are Unix milliseconds.
+#[derive(Serialize, Clone)]
+pub struct SessionSummary {
+    pub id: i64,
+    pub title: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub devices: serde_json::Value,
+    pub settings: serde_json::Value,
+    pub segment_count: i64,
+    pub exchange_count: i64,
+    pub screenshot_count: i64,
+}
+
+/// Transcript segment; `start_ms`/`end_ms` are relative to the session start.
+#[derive(Serialize, Clone)]
+pub struct StoredSegment {
+    pub id: i64,
+    pub speaker: String,
+    pub text: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub confidence: Option<f64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct StoredExchange {
+    pub id: i64,
+    pub created_at: i64,
+    pub kind: String,
+    pub model: Option<String>,
+    pub prompt: String,
+    pub response: String,
+}
+
+/// Stored screenshot. Fields added with screenshot history are `None` for
+/// older captures.
+#[derive(Serialize, Clone)]
+pub struct StoredScreenshot {
+    pub id: i64,
+    pub session_id: i64,
+    pub captured_at: i64,
+    pub path: String,
+    pub thumb_path: Option<String>,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: Option<i64>,
+    /// Size of the capture before scaling.
+    pub source_width: Option<u32>,
+    pub source_height: Option<u32>,
+    /// Display ID or mode; `None` for window captures and clipboard images.
+    pub display: Option<serde_json::Value>,
+    pub window: Option<i64>,
+    pub region: Option<serde_json::Value>,
+    pub ocr_text: Option<String>,
+}
+
+/// A capture to store with `add_screenshot`.
+pub struct NewScreenshot<'a> {
+    pub image: &'a [u8],
+    pub extension: &'a str,
+    pub mime_type: &'a str,
+    pub width: u32,
+    pub height: u32,
+    /// JPEG preview.
+    pub thumbnail: Option<&'a [u8]>,
+    pub source_width: u32,
+    pub source_height: u32,
+    pub display: Option<serde_json::Value>,
+    pub window: Option<i64>,
+    pub region: Option<serde_json::Value>,
+}
+
+/// An audit log entry: one value redacted from a model request (`target` is
+/// `request`, `kind` the request kind) or masked in a screenshot (`screenshot`,
+/// `ref_id` its ID).
+#[derive(Serialize, Clone)]
+pub struct RedactionEntry {
+    pub id: i64,
+    pub session_id: Option<i64>,
+    pub created_at: i64,
+    pub target: String,
+    pub kind: Option<String>,
+    pub ref_id: Option<i64>,
+    pub rule: String,
+    pub length: i64,
+}
+
+/// A stored screenshot's image, as returned by `get_screenshot`.
+#[derive(Serialize, Clone)]
+pub struct ScreenshotImage {
+    pub id: i64,
+    pub mime_type: String,
+    /// Base64 of the file.
+    pub data: String,
+}
+
+/// Everything recorded for one session, as returned by `open_session`.
+#[derive(Serialize, Clone)]
+pub struct SessionDetail {
+    pub session: SessionSummary,
+    pub segments: Vec<StoredSegment>,
+    pub exchanges: Vec<StoredExchange>,
+    pub screenshots: Vec<StoredScreenshot>,
+}
+
+struct ActiveSession {
+    id: i64,
+    started_at: i64,
+}
+
+/// Managed state: the session database plus the session currently recording.
+pub struct SessionStore {
+    conn: Mutex<Connection>,
+    active: Mutex<Option<ActiveSession>>,
+    /// Root for per-session files (`<root>/<session id>/...`).
+    files_dir: PathBuf,
+}
+
+pub fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+impl SessionStore {
+    /// Open (or create) `sessions.db` in `data_dir` and run pending migrations.
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        std::fs::create_dir_all(data_dir)
+            .map_err(|e| format!("Failed to create {}: {}", data_dir.display(), e))?;
+        let db_path = data_dir.join("sessions.db");
+        let mut conn = Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open {}: {}", db_path.display(), e))?;
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")
+            .map_err(|e| format!("Failed to configure session database: {}", e))?;
+        migrate(&mut conn)?;
+
+        Ok(SessionStore {
+            conn: Mutex::new(conn),
+            active: Mutex::new(None),
+            files_dir: data_dir.join("sessions"),
+        })
+    }
+
+    /// Start recording a new session and make it the active one.
+    pub fn begin(&self, devices: &[String], settings: &serde_json::Value) -> Result<i64, String> {
+        let started_at = now_ms();
+        let title = format!("Session {}", chrono::Local::now().format("%Y-%m-%d %H:%M"));
+        let devices = serde_json::to_string(devices).unwrap_or_else(|_| "[]".into());
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO sessions (title, started_at, devices, settings) VALUES (?1, ?2, ?3, ?4)",
+            params![title, started_at, devices, settings.to_string()],
+        )
+        .map_err(|e| format!("Failed to create session: {}", e))?;
+        let id = conn.last_insert_rowid();
+
+        *self.active.lock().unwrap() = Some(ActiveSession { id, started_at });
+        println!("[Sessions] Started session {} ('{}')", id, title);
+        Ok(id)
+    }
+
+    /// Close the active session, if any.
+    pub fn end(&self) -> Result<(), String> {
+        let Some(active) = self.active.lock().unwrap().take() else { return Ok(()) };
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE sessions SET ended_at = ?1 WHERE id = ?2", params![now_ms(), active.id])
+            .map_err(|e| format!("Failed to end session: {}", e))?;
+        println!("[Sessions] Ended session {}", active.id);
+        Ok(())
+    }
+
+    pub fn active_id(&self) -> Option<i64> {
+        self.active.lock().unwrap().as_ref().map(|a| a.id)
+    }
+
+    /// Record a final transcript segment in the active session. `start_unix_ms`
+    /// and `end_unix_ms` are wall-clock times; they are stored relative to the
+    /// session start.
+    pub fn add_segment(
+        &self,
+        speaker: &str,
+        text: &str,
+        start_unix_ms: i64,
+        end_unix_ms: i64,
+        confidence: Option<f32>,
+    ) -> Result<(), String> {
+        let (id, started_at) = match self.active.lock().unwrap().as_ref() {
+            Some(a) => (a.id, a.started_at),
+            None => return Ok(()),
+        };
+        let start_ms = (start_unix_ms - started_at).max(0);
+        let end_ms = (end_unix_ms - started_at).max(start_ms);
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO segments (session_id, speaker, text, start_ms, end_ms, confidence)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![id, speaker, text, start_ms, end_ms, confidence],
+            )
+            .map_err(|e| format!("Failed to store transcript segment: {}", e))?;
+        Ok(())
+    }
+
+    /// Record an assistant request/response pair in the active session.
+    pub fn add_exchange(&self, kind: &str, model: Option<&str>, prompt: &str, response: &str) -> Result<(), String> {
+        let Some(id) = self.active_id() else { return Ok(()) };
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO exchanges (session_id, created_at, kind, model, prompt, response)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![id, now_ms(), kind, model, prompt, response],
+            )
+            .map_err(|e| format!("Failed to store exchange: {}", e))?;
+        Ok(())
+    }
+
+    /// Save an encoded screenshot and its thumbnail under the active session's
+    /// directory and record it. Files are named after the row ID, so captures
+    /// in the same millisecond don't overwrite each other. Returns the new row
+    /// ID, or `None` when no session is active.
+    pub fn add_screenshot(&self, shot: &NewScreenshot) -> Result<Option<i64>, String> {
+        let Some(session_id) = self.active_id() else { return Ok(None) };
+        let captured_at = now_ms();
+
+        let dir = self.files_dir.join(session_id.to_string()).join("screenshots");
+        let thumbs = dir.join("thumbs");
+        std::fs::create_dir_all(&thumbs).map_err(|e| format!("Failed to create {}: {}", thumbs.display(), e))?;
+
+        let mut conn = self.conn.lock().unwrap();
+        // Dropped without a commit on any error below, so no row is left without its file.
+        let tx = conn.transaction().map_err(|e| format!("Failed to store screenshot: {}", e))?;
+        tx.execute(
+            "INSERT INTO screenshots
+                 (session_id, captured_at, path, width, height, mime_type, bytes,
+                  source_width, source_height, display, window, region)
+             VALUES (?1, ?2, '', ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                session_id,
+                captured_at,
+                shot.width,
+                shot.height,
+                shot.mime_type,
+                shot.image.len() as i64,
+                shot.source_width,
+                shot.source_height,
+                shot.display.as_ref().map(|d| d.to_string()),
+                shot.window,
+                shot.region.as_ref().map(|r| r.to_string()),
+            ],
+        )
+        .map_err(|e| format!("Failed to store screenshot: {}", e))?;
+        let id = tx.last_insert_rowid();
+
+        let name = format!("{}-{}", captured_at, id);
+        let path = dir.join(format!("{}.{}", name, shot.extension));
+        let thumb_path = shot.thumbnail.map(|_| thumbs.join(format!("{}.jpg", name)));
+        let result = write_screenshot_files(&path, shot.image, thumb_path.as_deref().zip(shot.thumbnail))
+            .and_then(|()| {
+                tx.execute(
+                    "UPDATE screenshots SET path = ?1, thumb_path = ?2 WHERE id = ?3",
+                    params![
+                        path.to_string_lossy(),
+                        thumb_path.as_ref().map(|p| p.to_string_lossy().into_owned()),
+                        id
+                    ],
+                )
+                .map_err(|e| format!("Failed to store screenshot: {}", e))?;
+                tx.commit().map_err(|e| format!("Failed to store screenshot: {}", e))
+            });
+        if let Err(e) = result {
+            for written in std::iter::once(&path).chain(thumb_path.as_ref()) {
+                let _ = std::fs::remove_file(written);
+            }
+            return Err(e);
+        }
+        Ok(Some(id))
+    }
+
+    /// Stored screenshots, newest first: one session's, or across all sessions.
+    pub fn list_screenshots(&self, session_id: Option<i64>, limit: usize) -> Result<Vec<StoredScreenshot>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} WHERE ?1 IS NULL OR session_id = ?1 ORDER BY captured_at DESC, id DESC LIMIT ?2",
+                SCREENSHOT_QUERY
+            ))
+            .map_err(|e| format!("Failed to list screenshots: {}", e))?;
+        let rows = stmt
+            .query_map(params![session_id, limit as i64], screenshot_from_row)
+            .map_err(|e| format!("Failed to list screenshots: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read screenshot: {}", e))
+    }
+
+    pub fn screenshot(&self, id: i64) -> Result<StoredScreenshot, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(&format!("{} WHERE id = ?1", SCREENSHOT_QUERY), [id], screenshot_from_row)
+            .optional()
+            .map_err(|e| format!("Failed to load screenshot: {}", e))?
+            .ok_or_else(|| format!("Screenshot {} not found", id))
+    }
+
+    /// Read a screenshot file, or its thumbnail when `thumbnail` is set and
+    /// one was stored.
+    pub fn screenshot_image(&self, id: i64, thumbnail: bool) -> Result<ScreenshotImage, String> {
+        use base64::{Engine as _, engine::general_purpose};
+
+        let shot = self.screenshot(id)?;
+        let (path, mime_type) = match shot.thumb_path {
+            Some(thumb_path) if thumbnail => (thumb_path, "image/jpeg".to_string()),
+            _ => (shot.path, shot.mime_type),
+        };
+        let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        Ok(ScreenshotImage { id, mime_type, data: general_purpose::STANDARD.encode(bytes) })
+    }
+
+    /// Delete a screenshot, its files and its search entry.
+    pub fn delete_screenshot(&self, id: i64) -> Result<(), String> {
+        let shot = self.screenshot(id)?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM screenshots WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete screenshot: {}", e))?;
+        for path in std::iter::once(&shot.path).chain(shot.thumb_path.as_ref()) {
+            if let Err(e) = std::fs::remove_file(path) {
+                eprintln!("[Sessions] Failed to remove {}: {}", path, e);
+            }
+        }
+        println!("[Sessions] Deleted screenshot {}", id);
+        Ok(())
+    }
+
+    /// Copy screenshots into `dir` as `screenshot-<local time>-<id>.<ext>`,
+    /// with their OCR text beside them as `.txt`. Returns the written paths.
+    pub fn export_screenshots(&self, ids: &[i64], dir: &Path) -> Result<Vec<String>, String> {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let mut written = Vec::new();
+        for &id in ids {
+            let shot = self.screenshot(id)?;
+            let time = chrono::DateTime::from_timestamp_millis(shot.captured_at)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y%m%d-%H%M%S").to_string())
+                .unwrap_or_default();
+            let source = Path::new(&shot.path);
+            let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("png");
+            let target = dir.join(format!("screenshot-{}-{}.{}", time, id, extension));
+            std::fs::copy(source, &target)
+                .map_err(|e| format!("Failed to copy {} to {}: {}", shot.path, target.display(), e))?;
+            written.push(target.to_string_lossy().into_owned());
+
+            if let Some(text) = shot.ocr_text.filter(|t| !t.trim().is_empty()) {
+                let text_path = target.with_extension("txt");
+                std::fs::write(&text_path, text)
+                    .map_err(|e| format!("Failed to write {}: {}", text_path.display(), e))?;
+                written.push(text_path.to_string_lossy().into_owned());
+            }
+        }
+        println!("[Sessions] Exported {} screenshots to {}", ids.len(), dir.display());
+        Ok(written)
+    }
+
+    /// Attach OCR text to a stored screenshot, which also indexes it for search.
+    pub fn set_screenshot_text(&self, id: i64, text: &str) -> Result<(), String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE screenshots SET ocr_text = ?1 WHERE id = ?2", params![text, id])
+            .map_err(|e| format!("Failed to store screenshot text: {}", e))?;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Result<Vec<SessionSummary>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!("{} ORDER BY s.started_at DESC", SUMMARY_QUERY))
+            .map_err(|e| format!("Failed to list sessions: {}", e))?;
+        let rows = stmt
+            .query_map([], summary_from_row)
+            .map_err(|e| format!("Failed to list sessions: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read session: {}", e))
+    }
+
+    pub fn open_session(&self, id: i64) -> Result<SessionDetail, String> {
+        let conn = self.conn.lock().unwrap();
+        let session = conn
+            .query_row(&format!("{} WHERE s.id = ?1", SUMMARY_QUERY), [id], summary_from_row)
+            .optional()
+            .map_err(|e| format!("Failed to load session: {}", e))?
+            .ok_or_else(|| format!("Session {} not found", id))?;
+
+        let segments = query_all(
+            &conn,
+            "SELECT id, speaker, text, start_ms, end_ms, confidence
+             FROM segments WHERE session_id = ?1 ORDER BY start_ms, id",
+            id,
+            |r| {
+                Ok(StoredSegment {
+                    id: r.get(0)?,
+                    speaker: r.get(1)?,
+                    text: r.get(2)?,
+                    start_ms: r.get(3)?,
+                    end_ms: r.get(4)?,
+                    confidence: r.get(5)?,
+                })
+            },
+        )?;
+        let exchanges = query_all(
+            &conn,
+            "SELECT id, created_at, kind, model, prompt, response
+             FROM exchanges WHERE session_id = ?1 ORDER BY created_at, id",
+            id,
+            |r| {
+                Ok(StoredExchange {
+                    id: r.get(0)?,
+                    created_at: r.get(1)?,
+                    kind: r.get(2)?,
+                    model: r.get(3)?,
+                    prompt: r.get(4)?,
+                    response: r.get(5)?,
+                })
+            },
+        )?;
+        let screenshots = query_all(
+            &conn,
+            &format!("{} WHERE session_id = ?1 ORDER BY captured_at, id", SCREENSHOT_QUERY),
+            id,
+            screenshot_from_row,
+        )?;
+
+        Ok(SessionDetail { session, segments, exchanges, screenshots })
+    }
+
+    /// Transcript segments of the active session with an ID above `after_id`,
+    /// in the order they were recorded, at most the newest `limit`.
+    pub fn segments_since(&self, after_id: i64, limit: usize) -> Result<Vec<StoredSegment>, String> {
+        let Some(id) = self.active_id() else { return Ok(Vec::new()) };
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT id, speaker, text, start_ms, end_ms, confidence
+                 FROM segments WHERE session_id = ?1 AND id > ?2
+                 ORDER BY id DESC LIMIT ?3",
+            )
+            .map_err(|e| format!("Failed to query session: {}", e))?;
+        let rows = stmt
+            .query_map(params![id, after_id, limit as i64], |r| {
+                Ok(StoredSegment {
+                    id: r.get(0)?,
+                    speaker: r.get(1)?,
+                    text: r.get(2)?,
+                    start_ms: r.get(3)?,
+                    end_ms: r.get(4)?,
+                    confidence: r.get(5)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query session: {}", e))?;
+        let mut segments = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read session row: {}", e))?;
+        segments.reverse();
+        Ok(segments)
+    }
+
+    /// OCR text of the active session's latest screenshot that has any.
+    pub fn latest_screenshot_text(&self) -> Result<Option<String>, String> {
+        let Some(id) = self.active_id() else { return Ok(None) };
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT ocr_text FROM screenshots
+                 WHERE session_id = ?1 AND ocr_text IS NOT NULL
+                 ORDER BY captured_at DESC LIMIT 1",
+                [id],
+                |r| r.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to load screenshot text: {}", e))
+    }
+
+    pub fn search(&self, query: &crate::search::SearchQuery) -> Result<Vec<crate::search::SearchHit>, String> {
+        crate::search::run(&self.conn.lock().unwrap(), query)
+    }
+
+    /// Record the usage of a model request, in the active session if there is one.
+    pub fn add_usage(&self, record: &crate::llm::usage::UsageRecord) -> Result<(), String> {
+        let session_id = self.active_id();
+        crate::llm::usage::insert(&self.conn.lock().unwrap(), session_id, record)
+    }
+
+    /// USD spent today, or in the active session when `session` is set.
+    pub fn usage_spent(&self, session: bool) -> Result<f64, String> {
+        if !session {
+            return crate::llm::usage::spent(&self.conn.lock().unwrap(), None);
+        }
+        let Some(id) = self.active_id() else { return Ok(0.0) };
+        crate::llm::usage::spent(&self.conn.lock().unwrap(), Some(id))
+    }
+
+    pub fn usage_report(&self, budget: crate::llm::usage::BudgetStatus) -> Result<crate::llm::usage::UsageReport, String> {
+        crate::llm::usage::report(&self.conn.lock().unwrap(), budget)
+    }
+
+    pub fn add_redactions(
+        &self,
+        target: &str,
+        kind: Option<&str>,
+        ref_id: Option<i64>,
+        redactions: &[crate::redact::Redaction],
+    ) -> Result<(), String> {
+        let session_id = self.active_id();
+        let created_at = now_ms();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| format!("Failed to log redactions: {}", e))?;
+        for r in redactions {
+            tx.execute(
+                "INSERT INTO redactions (session_id, created_at, target, kind, ref_id, rule, length)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![session_id, created_at, target, kind, ref_id, r.rule, r.length as i64],
+            )
+            .map_err(|e| format!("Failed to log redactions: {}", e))?;
+        }
+        tx.commit().map_err(|e| format!("Failed to log redactions: {}", e))
+    }
+
+    /// Redaction audit log, newest first: one session's or all of it.
+    pub fn redaction_log(&self, session_id: Option<i64>, limit: usize) -> Result<Vec<RedactionEntry>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, session_id, created_at, target, kind, ref_id, rule, length
+                 FROM redactions WHERE ?1 IS NULL OR session_id = ?1
+                 ORDER BY created_at DESC, id DESC LIMIT ?2",
+            )
+            .map_err(|e| format!("Failed to read redaction log: {}", e))?;
+        let rows = stmt
+            .query_map(params![session_id, limit as i64], |r| {
+                Ok(RedactionEntry {
+                    id: r.get(0)?,
+                    session_id: r.get(1)?,
+                    created_at: r.get(2)?,
+                    target: r.get(3)?,
+                    kind: r.get(4)?,
+                    ref_id: r.get(5)?,
+                    rule: r.get(6)?,
+                    length: r.get(7)?,
+                })
+            })
+            .map_err(|e| format!("Failed to read redaction log: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read redaction: {}", e))
+    }
+
+    pub fn rename(&self, id: i64, title: &str) -> Result<(), String> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err("Session title cannot be empty".to_string());
+        }
+        let changed = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE sessions SET title = ?1 WHERE id = ?2", params![title, id])
+            .map_err(|e| format!("Failed to rename session: {}", e))?;
+        if changed == 0 {
+            return Err(format!("Session {} not found", id));
+        }
+        Ok(())
+    }
+
+    /// Delete a session with everything recorded in it, including files on disk.
+    pub fn delete(&self, id: i64) -> Result<(), String> {
+        if self.active_id() == Some(id) {
+            return Err("Cannot delete the session that is currently recording".to_string());
+        }
+        let changed = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM sessions WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete session: {}", e))?;
+        if changed == 0 {
+            return Err(format!("Session {} not found", id));
+        }
+
+        let dir = self.files_dir.join(id.to_string());
+        if dir.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                eprintln!("[Sessions] Failed to remove {}: {}", dir.display(), e);
+            }
+        }
+        println!("[Sessions] Deleted session {}", id);
+        Ok(())
+    }
+}
+
+/// Record a final transcript segment in the active session, if there is one.
+/// Used by the transcriber, which only has an `AppHandle`.
+pub fn record_segment(
+    app: &AppHandle,
+    speaker: &str,
+    text: &str,
+    start_unix_ms: i64,
+    end_unix_ms: i64,
+    confidence: Option<f32>,
+) {
+    let Some(store) = app.try_state::<SessionStore>() else { return };
+    if let Err(e) = store.add_segment(speaker, text, start_unix_ms, end_unix_ms, confidence) {
+        eprintln!("[Sessions] {}", e);
+    }
+}
+
+const SUMMARY_QUERY: &str = "
+    SELECT s.id, s.title, s.started_at, s.ended_at, s.devices, s.settings,
+           (SELECT COUNT(*) FROM segments    WHERE session_id = s.id),
+           (SELECT COUNT(*) FROM exchanges   WHERE session_id = s.id),
+           (SELECT COUNT(*) FROM screenshots WHERE session_id = s.id)
+    FROM sessions s";
+
+fn summary_from_row(r: &rusqlite::Row) -> rusqlite::Result<SessionSummary> {
+    let devices: String = r.get(4)?;
+    let settings: String = r.get(5)?;
+    Ok(SessionSummary {
+        id: r.get(0)?,
+        title: r.get(1)?,
+        started_at: r.get(2)?,
+        ended_at: r.get(3)?,
+        devices: serde_json::from_str(&devices).unwrap_or(serde_json::Value::Null),
+        settings: serde_json::from_str(&settings).unwrap_or(serde_json::Value::Null),
+        segment_count: r.get(6)?,
+        exchange_count: r.get(7)?,
+        screenshot_count: r.get(8)?,
+    })
+}
+
+const SCREENSHOT_QUERY: &str = "
+    SELECT id, session_id, captured_at, path, thumb_path, mime_type, width, height, bytes,
+           source_width, source_height, display, window, region, ocr_text
+    FROM screenshots";
+
+/// Write a screenshot and, if given, its thumbnail.
+fn write_screenshot_files(path: &Path, image: &[u8], thumbnail: Option<(&Path, &[u8])>) -> Result<(), String> {
+    std::fs::write(path, image).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    if let Some((thumb_path, thumbnail)) = thumbnail {
+        std::fs::write(thumb_path, thumbnail).map_err(|e| format!("Failed to write {}: {}", thumb_path.display(), e))?;
+    }
+    Ok(())
+}
+
+fn screenshot_from_row(r: &rusqlite::Row) -> rusqlite::Result<StoredScreenshot> {
+    let json = |i: usize| -> rusqlite::Result<Option<serde_json::Value>> {
+        Ok(r.get::<_, Option<String>>(i)?.and_then(|s| serde_json::from_str(&s).ok()))
+    };
+    let path: String = r.get(3)?;
+    // Older rows have no MIME type; their extension tells.
+    let mime_type = r.get::<_, Option<String>>(5)?.unwrap_or_else(|| {
+        match Path::new(&path).extension().and_then(|e| e.to_str()) {
+            Some("jpg") => "image/jpeg",
+            Some("webp") => "image/webp",
+            _ => "image/png",
+        }
+        .to_string()
+    });
+    Ok(StoredScreenshot {
+        id: r.get(0)?,
+        session_id: r.get(1)?,
+        captured_at: r.get(2)?,
+        path,
+        thumb_path: r.get(4)?,
+        mime_type,
+        width: r.get(6)?,
+        height: r.get(7)?,
+        bytes: r.get(8)?,
+        source_width: r.get(9)?,
+        source_height: r.get(10)?,
+        display: json(11)?,
+        window: r.get(12)?,
+        region: json(13)?,
+        ocr_text: r.get(14)?,
+    })
+}
+
+fn query_all<T>(
+    conn: &Connection,
+    sql: &str,
+    session_id: i64,
+    map: impl FnMut(&rusqlite::Row) -> rusqlite::Result<T>,
+) -> Result<Vec<T>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("Failed to query session: {}", e))?;
+    let rows = stmt
+        .query_map([session_id], map)
+        .map_err(|e| format!("Failed to query session: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read session row: {}", e))
+}
+
+pub(crate) fn migrate(conn: &mut Connection) -> Result<(), String> {
+    let current: usize = conn
+        .query_row("PRAGMA user_version", [], |r| r.get::<_, i64>(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))? as usize;
+
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+        let version = i + 1;
+        let tx = conn.transaction().map_err(|e| format!("Migration {} failed: {}", version, e))?;
+        tx.execute_batch(sql).map_err(|e| format!("Migration {} failed: {}", version, e))?;
+        tx.pragma_update(None, "user_version", version as i64)
+            .map_err(|e| format!("Migration {} failed: {}", version, e))?;
+        tx.commit().map_err(|e| format!("Migration {} failed: {}", version, e))?;
+        println!("[Sessions] Applied schema migration {}", version);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> SessionStore {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        let files_dir = std::env::temp_dir().join(format!("sessions-test-{}-{}", std::process::id(), name));
+        SessionStore { conn: Mutex::new(conn), active: Mutex::new(None), files_dir }
+    }
+
+    fn shot<'a>(image: &'a [u8], thumbnail: &'a [u8]) -> NewScreenshot<'a> {
+        NewScreenshot {
+            image,
+            extension: "png",
+            mime_type: "image/png",
+            width: 4,
+            height: 2,
+            thumbnail: Some(thumbnail),
+            source_width: 8,
+            source_height: 4,
+            display: None,
+            window: None,
+            region: None,
+        }
+    }
+
+    fn count(store: &SessionStore, table: &str) -> i64 {
+        let conn = store.conn.lock().unwrap();
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0)).unwrap()
+    }
+
+    /// Files under the screenshot directory, thumbnails included.
+    fn files(dir: &Path) -> usize {
+        std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .map(|e| e.unwrap().path())
+                    .map(|p| if p.is_dir() { files(&p) } else { 1 })
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn migrations_apply_up_to_the_latest_version_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        let version = |conn: &Connection| conn.query_row("PRAGMA user_version", [], |r| r.get::<_, i64>(0)).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len() as i64);
+        // Re-running would fail on CREATE TABLE if anything were applied twice.
+        migrate(&mut conn).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len() as i64);
+        for table in ["sessions", "segments", "exchanges", "screenshots", "search_index", "usage", "redactions"] {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get::<_, i64>(0)).unwrap();
+        }
+    }
+
+    #[test]
+    fn failed_screenshot_insert_leaves_no_row_or_files() {
+        let store = store("screenshots");
+        let session = store.begin(&[], &serde_json::Value::Null).unwrap();
+        let dir = store.files_dir.join(session.to_string()).join("screenshots");
+
+        let id = store.add_screenshot(&shot(b"png", b"jpg")).unwrap().unwrap();
+        let stored = store.screenshot(id).unwrap();
+        assert!(stored.path.ends_with(&format!("-{}.png", id)));
+        assert_eq!(files(&dir), 2);
+
+        store
+            .conn
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "CREATE TRIGGER fail_update BEFORE UPDATE OF path ON screenshots
+                 BEGIN SELECT RAISE(ABORT, 'disk full'); END;",
+            )
+            .unwrap();
+        assert!(store.add_screenshot(&shot(b"png", b"jpg")).is_err());
+        assert_eq!(count(&store, "screenshots"), 1);
+        assert_eq!(files(&dir), 2);
+        std::fs::remove_dir_all(&store.files_dir).unwrap();
+    }
+
+    #[test]
+    fn segments_since_returns_the_newest_in_recorded_order() {
+        let store = store("segments");
+        assert!(store.segments_since(0, 10).unwrap().is_empty());
+        store.begin(&[], &serde_json::Value::Null).unwrap();
+        let now = now_ms();
+        for (i, text) in ["one", "two", "three", "four"].iter().enumerate() {
+            store.add_segment("me", text, now + i as i64, now + i as i64 + 1, None).unwrap();
+        }
+
+        let texts = |segments: Vec<StoredSegment>| segments.into_iter().map(|s| s.text).collect::<Vec<_>>();
+        assert_eq!(texts(store.segments_since(0, 10).unwrap()), ["one", "two", "three", "four"]);
+        assert_eq!(texts(store.segments_since(0, 2).unwrap()), ["three", "four"]);
+        let first = store.segments_since(0, 10).unwrap()[0].id;
+        assert_eq!(texts(store.segments_since(first, 10).unwrap()), ["two", "three", "four"]);
+    }
+}
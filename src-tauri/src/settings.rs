@@ -0,0 +1,30 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/// The tauri-plugin-store file the frontend keeps its settings in.
+pub const STORE_PATH: &str = "settings.dat";
+
+/// Read a setting saved by the frontend. Missing keys, unreadable stores and
+/// values of the wrong shape all come back as `None`.
+pub fn get<T: DeserializeOwned>(app: &AppHandle, key: &str) -> Option<T> {
+    let store = app.store(STORE_PATH).ok()?;
+    let value = store.get(key)?;
+    serde_json::from_value(value).ok()
+}
+
+/// Write a setting and persist the store.
+pub fn set<T: Serialize>(app: &AppHandle, key: &str, value: &T) -> Result<(), String> {
+    let store = app.store(STORE_PATH).map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(value).map_err(|e| format!("Failed to serialize {}: {}", key, e))?;
+    store.set(key, value);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// Delete a setting and persist the store.
+pub fn remove(app: &AppHandle, key: &str) -> Result<(), String> {
+    let store = app.store(STORE_PATH).map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.delete(key);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))
+}
@@ -0,0 +1,364 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+
+mod segment;
+
+use segment::{samples_to_ms, Segmenter, Utterance};
+
+/// Whisper models expect 16 kHz mono f32 PCM.
+const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+/// One revision of a recognised utterance, emitted as a `transcript-segment`
+/// event. Partial hypotheses share `segment_id` with the final one and carry
+/// an increasing `revision`, so the UI can replace them in place.
+/// Timestamps are milliseconds since local transcription was started.
+#[derive(Serialize, Clone, Debug)]
+pub struct TranscriptSegment {
+    pub segment_id: u64,
+    pub revision: u32,
+    pub is_final: bool,
+    pub speaker: String,
+    pub text: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Mean token probability reported by the decoder (0.0 – 1.0).
+    pub confidence: f32,
+}
+
+/// Captured audio handed over by the audio pipeline, one device callback at a time.
+struct AudioFrames {
+    speaker: String,
+    samples: Vec<i16>,
+    sample_rate: u32,
+    channels: u16,
+    /// Capture time of the last sample in the buffer.
+    captured_at: Instant,
+}
+
+struct TranscriberHandle {
+    tx: mpsc::Sender<AudioFrames>,
+}
+
+/// Managed state: the running local transcriber, if any.
+pub struct LocalTranscriber(Mutex<Option<TranscriberHandle>>);
+
+impl LocalTranscriber {
+    pub fn new() -> Self {
+        LocalTranscriber(Mutex::new(None))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.0.lock().unwrap().is_some()
+    }
+}
+
+/// Load a Whisper model and start the worker thread that segments captured
+/// audio into utterances and emits `transcript-segment` events for them.
+///
+/// `model` is either a path to a ggml model file or a file name inside
+/// `<app data>/models`; it defaults to `ggml-base.bin`. Blocks while the model
+/// is loaded.
+pub fn start(app: &AppHandle, model: Option<String>, language: Option<String>) -> Result<(), String> {
+    let state = app.state::<LocalTranscriber>();
+    if state.is_running() {
+        return Err("Local transcription is already running".to_string());
+    }
+
+    let model_path = resolve_model_path(app, model.as_deref())?;
+    println!("[STT] Loading model {}", model_path.display());
+    let mut engine = engine::Engine::load(&model_path, language)?;
+    println!("[STT] ✓ Model loaded");
+
+    let (tx, rx) = mpsc::channel::<AudioFrames>();
+    let epoch = Instant::now();
+    let epoch_unix_ms = crate::sessions::now_ms();
+    let app_handle = app.clone();
+
+    std::thread::Builder::new()
+        .name("local-stt".into())
+        .spawn(move || {
+            let mut tracks: HashMap<String, SpeakerTrack> = HashMap::new();
+            let mut next_segment_id: u64 = 1;
+
+            // Exits once the sender is dropped by `stop`.
+            while let Ok(first) = rx.recv() {
+                // Ingest everything that queued up while we were decoding, so
+                // a slow decode never makes us fall further behind.
+                for frames in std::iter::once(first).chain(rx.try_iter()) {
+                    tracks
+                        .entry(frames.speaker.clone())
+                        .or_insert_with(|| SpeakerTrack::new(&frames, epoch, epoch_unix_ms))
+                        .ingest(&frames, &mut next_segment_id);
+                }
+                for track in tracks.values_mut() {
+                    track.decode(&mut engine, &app_handle, false);
+                }
+            }
+
+            for track in tracks.values_mut() {
+                track.decode(&mut engine, &app_handle, true);
+            }
+            println!("[STT] Worker stopped");
+        })
+        .map_err(|e| format!("Failed to spawn transcription thread: {}", e))?;
+
+    *state.0.lock().unwrap() = Some(TranscriberHandle { tx });
+    Ok(())
+}
+
+/// Stop the worker. Audio already queued is still decoded and any open
+/// utterance is finalised.
+pub fn stop(app: &AppHandle) {
+    if app.state::<LocalTranscriber>().0.lock().unwrap().take().is_some() {
+        println!("[STT] Stopping local transcription");
+    }
+}
+
+/// Hand freshly captured samples from the capture pipeline to the local
+/// transcriber. Does nothing when local transcription is not running.
+pub fn feed(app: &AppHandle, speaker: &str, samples: &[i16], sample_rate: u32, channels: u16) {
+    let Some(state) = app.try_state::<LocalTranscriber>() else { return };
+    let guard = state.0.lock().unwrap();
+    if let Some(handle) = guard.as_ref() {
+        let _ = handle.tx.send(AudioFrames {
+            speaker: speaker.to_string(),
+            samples: samples.to_vec(),
+            sample_rate,
+            channels,
+            captured_at: Instant::now(),
+        });
+    }
+}
+
+fn resolve_model_path(app: &AppHandle, model: Option<&str>) -> Result<PathBuf, String> {
+    let name = model.unwrap_or("ggml-base.bin");
+    let direct = PathBuf::from(name);
+    let path = if direct.is_absolute() {
+        direct
+    } else {
+        app.path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data dir: {}", e))?
+            .join("models")
+            .join(name)
+    };
+    if !path.exists() {
+        return Err(format!("Whisper model not found at {}", path.display()));
+    }
+    Ok(path)
+}
+
+/// Per-speaker segmentation and decoding state.
+struct SpeakerTrack {
+    speaker: String,
+    /// Wall-clock time of the transcriber epoch, for persisting segments.
+    epoch_unix_ms: i64,
+    segmenter: Segmenter,
+}
+
+impl SpeakerTrack {
+    fn new(first: &AudioFrames, epoch: Instant, epoch_unix_ms: i64) -> Self {
+        let frames_ms = first.samples.len() as u64 * 1000
+            / (first.sample_rate.max(1) as u64 * first.channels.max(1) as u64);
+        let captured_ms = first.captured_at.saturating_duration_since(epoch).as_millis() as u64;
+        SpeakerTrack {
+            speaker: first.speaker.clone(),
+            epoch_unix_ms,
+            segmenter: Segmenter::new(first.sample_rate, first.channels, captured_ms.saturating_sub(frames_ms)),
+        }
+    }
+
+    fn ingest(&mut self, frames: &AudioFrames, next_segment_id: &mut u64) {
+        self.segmenter.ingest(&frames.samples, next_segment_id);
+    }
+
+    /// Finalise ended utterances and refresh the partial hypothesis of the
+    /// active one if enough new audio has arrived. With `flush`, the active
+    /// utterance is finalised too.
+    fn decode(&mut self, engine: &mut engine::Engine, app: &AppHandle, flush: bool) {
+        for mut utt in self.segmenter.take_finished(flush) {
+            let end_ms = utt.speech_end_ms();
+            if let Some(segment) = decode_utterance(engine, &self.speaker, &mut utt, end_ms, true) {
+                if !segment.text.is_empty() {
+                    crate::sessions::record_segment(
+                        app,
+                        &segment.speaker,
+                        &segment.text,
+                        self.epoch_unix_ms + segment.start_ms as i64,
+                        self.epoch_unix_ms + segment.end_ms as i64,
+                        Some(segment.confidence),
+                    );
+                }
+                let _ = app.emit("transcript-segment", segment);
+            }
+        }
+
+        if let Some(utt) = self.segmenter.partial_due() {
+            let end_ms = utt.end_ms();
+            if let Some(segment) = decode_utterance(engine, &self.speaker, utt, end_ms, false) {
+                let _ = app.emit("transcript-segment", segment);
+            }
+        }
+    }
+}
+
+/// Decode the utterance's audio so far and build the next revision of its
+/// segment. Returns `None` when there is nothing worth emitting.
+fn decode_utterance(
+    engine: &mut engine::Engine,
+    speaker: &str,
+    utt: &mut Utterance,
+    end_ms: u64,
+    is_final: bool,
+) -> Option<TranscriptSegment> {
+    utt.decoded_len = utt.audio.len();
+    let started = Instant::now();
+    let hypotheses = match engine.transcribe(&utt.audio) {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("[STT] ✗ Transcription failed: {}", e);
+            return None;
+        }
+    };
+    println!(
+        "[STT] Segment {} ({} ms, {}) decoded in {:?}",
+        utt.segment_id,
+        samples_to_ms(utt.audio.len()),
+        if is_final { "final" } else { "partial" },
+        started.elapsed()
+    );
+
+    let text = hypotheses.iter().map(|h| h.text.as_str()).collect::<Vec<_>>().join(" ");
+    // An empty partial is just noise; an empty final still has to be sent if
+    // partials went out, so the UI can drop them.
+    if text.is_empty() && (!is_final || utt.revision == 0) {
+        return None;
+    }
+
+    let weight: u64 = hypotheses.iter().map(|h| (h.end_ms - h.start_ms).max(1)).sum();
+    let confidence = if weight > 0 {
+        hypotheses.iter().map(|h| h.confidence * (h.end_ms - h.start_ms).max(1) as f32).sum::<f32>() / weight as f32
+    } else {
+        0.0
+    };
+
+    utt.revision += 1;
+    Some(TranscriptSegment {
+        segment_id: utt.segment_id,
+        revision: utt.revision,
+        is_final,
+        speaker: speaker.to_string(),
+        text,
+        start_ms: utt.start_ms,
+        end_ms,
+        confidence,
+    })
+}
+
+/// A decoded segment, with times relative to the start of the decoded audio.
+struct Hypothesis {
+    text: String,
+    start_ms: u64,
+    end_ms: u64,
+    confidence: f32,
+}
+
+#[cfg(feature = "local-stt")]
+mod engine {
+    use super::Hypothesis;
+    use std::path::Path;
+    use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState};
+
+    pub struct Engine {
+        ctx: WhisperContext,
+        state: WhisperState,
+        language: Option<String>,
+        threads: i32,
+    }
+
+    impl Engine {
+        pub fn load(path: &Path, language: Option<String>) -> Result<Self, String> {
+            let path_str = path.to_str().ok_or("Model path is not valid UTF-8")?;
+            let ctx = WhisperContext::new_with_params(path_str, WhisperContextParameters::default())
+                .map_err(|e| format!("Failed to load Whisper model: {}", e))?;
+            let state = ctx
+                .create_state()
+                .map_err(|e| format!("Failed to create Whisper state: {}", e))?;
+            let threads = std::thread::available_parallelism()
+                .map(|n| n.get().min(8))
+                .unwrap_or(4) as i32;
+            Ok(Engine { ctx, state, language, threads })
+        }
+
+        pub fn transcribe(&mut self, pcm: &[f32]) -> Result<Vec<Hypothesis>, String> {
+            let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+            params.set_n_threads(self.threads);
+            params.set_language(Some(self.language.as_deref().unwrap_or("auto")));
+            params.set_no_context(true);
+            params.set_suppress_blank(true);
+            params.set_print_progress(false);
+            params.set_print_realtime(false);
+            params.set_print_special(false);
+            params.set_print_timestamps(false);
+
+            self.state.full(params, pcm).map_err(|e| e.to_string())?;
+
+            let eot = self.ctx.token_eot();
+            let n_segments = self.state.full_n_segments().map_err(|e| e.to_string())?;
+            let mut out = Vec::new();
+            for seg in 0..n_segments {
+                let text = self.state.full_get_segment_text_lossy(seg).map_err(|e| e.to_string())?;
+                let text = text.trim();
+                if text.is_empty() {
+                    continue;
+                }
+                // Timestamps are in 10 ms units.
+                let t0 = self.state.full_get_segment_t0(seg).map_err(|e| e.to_string())?.max(0) as u64 * 10;
+                let t1 = self.state.full_get_segment_t1(seg).map_err(|e| e.to_string())?.max(0) as u64 * 10;
+
+                let n_tokens = self.state.full_n_tokens(seg).map_err(|e| e.to_string())?;
+                let mut prob_sum = 0.0f32;
+                let mut prob_count = 0;
+                for t in 0..n_tokens {
+                    let id = self.state.full_get_token_id(seg, t).map_err(|e| e.to_string())?;
+                    if id >= eot {
+                        continue; // special / timestamp tokens
+                    }
+                    prob_sum += self.state.full_get_token_prob(seg, t).map_err(|e| e.to_string())?;
+                    prob_count += 1;
+                }
+
+                out.push(Hypothesis {
+                    text: text.to_string(),
+                    start_ms: t0,
+                    end_ms: t1.max(t0),
+                    confidence: if prob_count > 0 { prob_sum / prob_count as f32 } else { 0.0 },
+                });
+            }
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(not(feature = "local-stt"))]
+mod engine {
+    use super::Hypothesis;
+    use std::path::Path;
+
+    pub struct Engine;
+
+    impl Engine {
+        pub fn load(_path: &Path, _language: Option<String>) -> Result<Self, String> {
+            Err("Local transcription is not available in this build (enable the `local-stt` feature)".to_string())
+        }
+
+        pub fn transcribe(&mut self, _pcm: &[f32]) -> Result<Vec<Hypothesis>, String> {
+            unreachable!("Engine cannot be constructed without the `local-stt` feature")
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::VecDeque;
+
+use super::WHISPER_SAMPLE_RATE;
+
+// ──── Utterance segmentation (energy VAD on 16 kHz mono) ────
+const FRAME_MS: u64 = 30;
+const FRAME_LEN: usize = (WHISPER_SAMPLE_RATE as u64 * FRAME_MS / 1000) as usize;
+/// Frame RMS above which we treat the frame as speech (≈ -40 dBFS).
+const SPEECH_RMS: f32 = 0.01;
+/// Audio kept from before the first speech frame so word onsets aren't clipped.
+const PRE_ROLL_MS: u64 = 300;
+/// Silence that ends an utterance.
+const END_SILENCE_MS: u64 = 700;
+/// Trailing silence kept on a finished utterance; whisper handles it fine.
+const TAIL_MS: u64 = 200;
+/// How much new audio must arrive before a partial hypothesis is re-decoded.
+const PARTIAL_INTERVAL_MS: u64 = 1000;
+/// Whisper decodes at most 30 s per window; close utterances well before that.
+const MAX_UTTERANCE_MS: u64 = 25_000;
+
+pub(super) fn samples_to_ms(samples: usize) -> u64 {
+    samples as u64 * 1000 / WHISPER_SAMPLE_RATE as u64
+}
+
+fn ms_to_samples(ms: u64) -> usize {
+    (ms * WHISPER_SAMPLE_RATE as u64 / 1000) as usize
+}
+
+/// An utterance that is being spoken (or has just ended) on one track.
+pub(super) struct Utterance {
+    pub segment_id: u64,
+    pub revision: u32,
+    pub start_ms: u64,
+    pub audio: Vec<f32>,
+    /// Length of `audio` up to the end of the last speech frame.
+    speech_len: usize,
+    /// Length of `audio` when it was last decoded.
+    pub decoded_len: usize,
+    silence_ms: u64,
+}
+
+impl Utterance {
+    /// End of the last speech frame, in track time.
+    pub fn speech_end_ms(&self) -> u64 {
+        self.start_ms + samples_to_ms(self.speech_len)
+    }
+
+    /// End of the audio received so far, in track time.
+    pub fn end_ms(&self) -> u64 {
+        self.start_ms + samples_to_ms(self.audio.len())
+    }
+}
+
+/// Splits one speaker's audio into utterances: resampled to 16 kHz mono, cut
+/// into 30 ms frames and classified by energy.
+pub(super) struct Segmenter {
+    resampler: Resampler,
+    channels: u16,
+    /// Epoch-relative time of the first resampled sample.
+    base_ms: u64,
+    /// Resampled samples consumed so far (the track's clock).
+    consumed: usize,
+    /// Resampled samples not yet forming a full VAD frame.
+    carry: Vec<f32>,
+    pre_roll: VecDeque<Vec<f32>>,
+    active: Option<Utterance>,
+    ended: Vec<Utterance>,
+}
+
+impl Segmenter {
+    pub fn new(sample_rate: u32, channels: u16, base_ms: u64) -> Self {
+        Segmenter {
+            resampler: Resampler::new(sample_rate),
+            channels,
+            base_ms,
+            consumed: 0,
+            carry: Vec::new(),
+            pre_roll: VecDeque::new(),
+            active: None,
+            ended: Vec::new(),
+        }
+    }
+
+    /// Add interleaved samples as captured by the device.
+    pub fn ingest(&mut self, samples: &[i16], next_segment_id: &mut u64) {
+        let mono = downmix(samples, self.channels);
+        self.resampler.process(&mono, &mut self.carry);
+
+        let full = self.carry.len() / FRAME_LEN * FRAME_LEN;
+        let pending: Vec<f32> = self.carry.drain(..full).collect();
+        for frame in pending.chunks_exact(FRAME_LEN) {
+            self.push_frame(frame, next_segment_id);
+        }
+    }
+
+    fn push_frame(&mut self, frame: &[f32], next_segment_id: &mut u64) {
+        let frame_start_ms = self.base_ms + samples_to_ms(self.consumed);
+        self.consumed += frame.len();
+        let is_speech = rms(frame) > SPEECH_RMS;
+
+        match self.active.as_mut() {
+            None => {
+                if is_speech {
+                    let pre_roll_len: usize = self.pre_roll.iter().map(Vec::len).sum();
+                    let mut audio: Vec<f32> = self.pre_roll.drain(..).flatten().collect();
+                    audio.extend_from_slice(frame);
+                    self.active = Some(Utterance {
+                        segment_id: *next_segment_id,
+                        revision: 0,
+                        start_ms: frame_start_ms.saturating_sub(samples_to_ms(pre_roll_len)),
+                        speech_len: audio.len(),
+                        audio,
+                        decoded_len: 0,
+                        silence_ms: 0,
+                    });
+                    *next_segment_id += 1;
+                } else {
+                    self.pre_roll.push_back(frame.to_vec());
+                    while self.pre_roll.len() as u64 * FRAME_MS > PRE_ROLL_MS {
+                        self.pre_roll.pop_front();
+                    }
+                }
+            }
+            Some(utt) => {
+                utt.audio.extend_from_slice(frame);
+                if is_speech {
+                    utt.silence_ms = 0;
+                    utt.speech_len = utt.audio.len();
+                } else {
+                    utt.silence_ms += FRAME_MS;
+                }
+                if utt.silence_ms >= END_SILENCE_MS || samples_to_ms(utt.audio.len()) >= MAX_UTTERANCE_MS {
+                    let utt = self.active.take().unwrap();
+                    self.ended.push(utt);
+                }
+            }
+        }
+    }
+
+    /// Utterances ready for their final decode, trimmed to the speech plus a
+    /// short tail. With `flush`, the active utterance is finished too.
+    pub fn take_finished(&mut self, flush: bool) -> Vec<Utterance> {
+        if flush {
+            if let Some(utt) = self.active.take() {
+                self.ended.push(utt);
+            }
+        }
+        let mut finished = std::mem::take(&mut self.ended);
+        for utt in &mut finished {
+            let keep = (utt.speech_len + ms_to_samples(TAIL_MS)).min(utt.audio.len());
+            utt.audio.truncate(keep);
+        }
+        finished
+    }
+
+    /// The active utterance, when enough audio arrived since its last decode
+    /// to refresh the partial hypothesis.
+    pub fn partial_due(&mut self) -> Option<&mut Utterance> {
+        self.active
+            .as_mut()
+            .filter(|utt| samples_to_ms(utt.audio.len() - utt.decoded_len) >= PARTIAL_INTERVAL_MS)
+    }
+}
+
+fn rms(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+}
+
+/// Downmix interleaved i16 to mono f32 in -1.0..1.0.
+fn downmix(samples: &[i16], channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().map(|&s| s as f32).sum::<f32>() / (channels as f32 * i16::MAX as f32))
+        .collect()
+}
+
+/// Streaming linear resampler to 16 kHz that keeps its phase across buffers.
+struct Resampler {
+    ratio: f64,
+    /// Read position relative to `prev` (0.0 = `prev`, 1.0 = next input[0]).
+    pos: f64,
+    prev: Option<f32>,
+}
+
+impl Resampler {
+    fn new(sample_rate: u32) -> Self {
+        Resampler { ratio: sample_rate as f64 / WHISPER_SAMPLE_RATE as f64, pos: 0.0, prev: None }
+    }
+
+    fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        if input.is_empty() {
+            return;
+        }
+        if (self.ratio - 1.0).abs() < f64::EPSILON {
+            out.extend_from_slice(input);
+            return;
+        }
+
+        let offset = usize::from(self.prev.is_some());
+        let len = input.len() + offset;
+        let at = |i: usize| if i < offset { self.prev.unwrap_or(0.0) } else { input[i - offset] };
+
+        while self.pos + 1.0 < len as f64 {
+            let idx = self.pos as usize;
+            let frac = (self.pos - idx as f64) as f32;
+            let (a, b) = (at(idx), at(idx + 1));
+            out.push(a + (b - a) * frac);
+            self.pos += self.ratio;
+        }
+
+        // Re-anchor on the last input sample, which becomes the next `prev`.
+        self.pos -= (len - 1) as f64;
+        self.prev = input.last().copied();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ms` of a 440 Hz tone (speech) or silence at `rate`, mono.
+    fn audio(ms: u64, rate: u32, speech: bool) -> Vec<i16> {
+        let n = (ms * rate as u64 / 1000) as usize;
+        (0..n)
+            .map(|i| {
+                let t = i as f32 / rate as f32;
+                if speech { ((t * 440.0 * std::f32::consts::TAU).sin() * 8000.0) as i16 } else { 0 }
+            })
+            .collect()
+    }
+
+    /// Feed `ms` of audio in 10 ms device buffers.
+    fn feed(segmenter: &mut Segmenter, ms: u64, speech: bool, next_id: &mut u64) {
+        for chunk in audio(ms, 16_000, speech).chunks(160) {
+            segmenter.ingest(chunk, next_id);
+        }
+    }
+
+    #[test]
+    fn resampler_output_length_follows_the_ratio() {
+        for rate in [44_100u32, 48_000, 8_000] {
+            let input = vec![0.5f32; rate as usize];
+            let mut out = Vec::new();
+            let mut resampler = Resampler::new(rate);
+            for chunk in input.chunks(441) {
+                resampler.process(chunk, &mut out);
+            }
+            // The last input sample is held back until the next buffer.
+            assert!(out.len().abs_diff(16_000) <= 2, "{} Hz: {}", rate, out.len());
+        }
+        let mut out = Vec::new();
+        Resampler::new(16_000).process(&[0.1, 0.2], &mut out);
+        assert_eq!(out, [0.1, 0.2]);
+    }
+
+    #[test]
+    fn resampler_is_continuous_across_buffers() {
+        let input: Vec<f32> = (0..4800).map(|i| (i as f32 / 48.0).sin()).collect();
+        let mut whole = Vec::new();
+        Resampler::new(48_000).process(&input, &mut whole);
+
+        let mut chunked = Vec::new();
+        let mut resampler = Resampler::new(48_000);
+        for chunk in input.chunks(137) {
+            resampler.process(chunk, &mut chunked);
+        }
+        assert_eq!(whole.len(), chunked.len());
+        assert!(whole.iter().zip(&chunked).all(|(a, b)| (a - b).abs() < 1e-5));
+    }
+
+    #[test]
+    fn utterance_ends_after_700_ms_of_silence() {
+        let mut segmenter = Segmenter::new(16_000, 1, 0);
+        let mut next_id = 1;
+        feed(&mut segmenter, 600, false, &mut next_id);
+        feed(&mut segmenter, 1500, true, &mut next_id);
+        feed(&mut segmenter, 600, false, &mut next_id);
+        assert!(segmenter.take_finished(false).is_empty());
+
+        feed(&mut segmenter, 150, false, &mut next_id);
+        let finished = segmenter.take_finished(false);
+        assert_eq!(finished.len(), 1);
+        let utt = &finished[0];
+        assert_eq!(utt.segment_id, 1);
+        // The pre-roll reaches 300 ms back from the first speech frame.
+        assert_eq!(utt.start_ms, 300);
+        assert_eq!(utt.speech_end_ms(), 2100);
+        // Trimmed to the speech plus a 200 ms tail.
+        assert_eq!(utt.end_ms(), 2300);
+        assert_eq!(next_id, 2);
+    }
+
+    #[test]
+    fn long_speech_is_cut_at_the_cap() {
+        let mut segmenter = Segmenter::new(16_000, 1, 0);
+        let mut next_id = 1;
+        feed(&mut segmenter, 26_000, true, &mut next_id);
+        let finished = segmenter.take_finished(false);
+        assert_eq!(finished.len(), 1);
+        // Cut at the first frame boundary past the cap.
+        let cut = finished[0].end_ms();
+        assert!((MAX_UTTERANCE_MS..MAX_UTTERANCE_MS + FRAME_MS).contains(&cut), "{}", cut);
+        // Speech carries on in a new utterance, finished by a flush.
+        assert_eq!(next_id, 3);
+        let rest = segmenter.take_finished(true);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].segment_id, 2);
+        assert_eq!(rest[0].start_ms, cut);
+    }
+
+    #[test]
+    fn partials_are_due_every_second_of_new_audio() {
+        let mut segmenter = Segmenter::new(16_000, 1, 0);
+        let mut next_id = 1;
+        feed(&mut segmenter, 900, true, &mut next_id);
+        assert!(segmenter.partial_due().is_none());
+        feed(&mut segmenter, 150, true, &mut next_id);
+        let utt = segmenter.partial_due().unwrap();
+        utt.decoded_len = utt.audio.len();
+
+        feed(&mut segmenter, 600, true, &mut next_id);
+        assert!(segmenter.partial_due().is_none());
+        feed(&mut segmenter, 450, true, &mut next_id);
+        assert!(segmenter.partial_due().is_some());
+    }
+}
@@ -0,0 +1,391 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{AeadCore, ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::sessions::now_ms;
+use crate::settings;
+
+const VAULT_FILE: &str = "vault.json";
+const KEYRING_USER: &str = "vault-key";
+/// Encrypted with the master key so a wrong passphrase is detected on unlock.
+const CHECK_MARKER: &[u8] = b"vault-check";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum KeySource {
+    /// Random master key stored in the OS keyring.
+    Keyring,
+    /// Master key derived from a passphrase with Argon2id.
+    Passphrase { salt: String, m_cost: u32, t_cost: u32, p_cost: u32 },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct StoredCredential {
+    #[serde(flatten)]
+    sealed: Sealed,
+    /// Last four characters, for display.
+    hint: String,
+    updated_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct VaultFile {
+    version: u32,
+    key_source: KeySource,
+    check: Sealed,
+    credentials: BTreeMap<String, StoredCredential>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CredentialInfo {
+    pub name: String,
+    /// `…abcd`; the secret itself never leaves the backend.
+    pub hint: String,
+    pub updated_at: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct VaultStatus {
+    /// `keyring`, `passphrase`, or `none` before a vault has been created.
+    pub mode: String,
+    pub unlocked: bool,
+    /// No keyring is available and no passphrase has been set yet.
+    pub needs_passphrase: bool,
+    pub credentials: Vec<CredentialInfo>,
+}
+
+struct Inner {
+    file: Option<VaultFile>,
+    key: Option<[u8; 32]>,
+}
+
+/// Managed state: provider credentials encrypted at rest in
+/// `<app data>/vault.json` with ChaCha20-Poly1305. The master key lives in
+/// the OS keyring, or is derived from a passphrase when there is none.
+pub struct Vault {
+    path: PathBuf,
+    service: String,
+    inner: Mutex<Inner>,
+}
+
+impl Vault {
+    /// Load the vault and unlock it from the keyring when possible. `service`
+    /// names the keyring entry (the app identifier).
+    pub fn open(data_dir: &Path, service: &str) -> Result<Self, String> {
+        let path = data_dir.join(VAULT_FILE);
+        let file: Option<VaultFile> = if path.exists() {
+            let json = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            Some(serde_json::from_str(&json).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?)
+        } else {
+            None
+        };
+        let vault = Vault { path, service: service.to_string(), inner: Mutex::new(Inner { file, key: None }) };
+
+        let mut inner = vault.inner.lock().unwrap();
+        match inner.file.as_ref().map(|f| f.key_source.clone()) {
+            Some(KeySource::Keyring) => match vault.keyring_key() {
+                Ok(key) if verify(&key, inner.file.as_ref().unwrap()) => {
+                    inner.key = Some(key);
+                    println!("[Vault] ✓ Unlocked from the OS keyring");
+                }
+                Ok(_) => eprintln!("[Vault] ✗ Keyring key does not match the vault"),
+                Err(e) => eprintln!("[Vault] ✗ Keyring unavailable: {}", e),
+            },
+            Some(KeySource::Passphrase { .. }) => println!("[Vault] Locked; waiting for passphrase"),
+            None => match vault.create_keyring_vault() {
+                Ok((file, key)) => {
+                    vault.write(&file)?;
+                    inner.file = Some(file);
+                    inner.key = Some(key);
+                    println!("[Vault] ✓ Created vault with a keyring key");
+                }
+                Err(e) => eprintln!("[Vault] No keyring ({}); a passphrase is required", e),
+            },
+        }
+        drop(inner);
+        Ok(vault)
+    }
+
+    pub fn status(&self) -> VaultStatus {
+        let inner = self.inner.lock().unwrap();
+        let mode = match inner.file.as_ref().map(|f| &f.key_source) {
+            Some(KeySource::Keyring) => "keyring",
+            Some(KeySource::Passphrase { .. }) => "passphrase",
+            None => "none",
+        };
+        let credentials = inner
+            .file
+            .iter()
+            .flat_map(|f| f.credentials.iter())
+            .map(|(name, c)| CredentialInfo { name: name.clone(), hint: c.hint.clone(), updated_at: c.updated_at })
+            .collect();
+        VaultStatus {
+            mode: mode.to_string(),
+            unlocked: inner.key.is_some(),
+            needs_passphrase: inner.file.is_none(),
+            credentials,
+        }
+    }
+
+    /// Unlock a passphrase vault, or create one when no vault exists yet.
+    pub fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        if passphrase.chars().count() < 8 {
+            return Err("Passphrase must be at least 8 characters".to_string());
+        }
+        let mut inner = self.inner.lock().unwrap();
+        match inner.file.as_ref().map(|f| f.key_source.clone()) {
+            Some(KeySource::Passphrase { salt, m_cost, t_cost, p_cost }) => {
+                let salt = decode(&salt)?;
+                let key = derive_key(passphrase, &salt, m_cost, t_cost, p_cost)?;
+                if !verify(&key, inner.file.as_ref().unwrap()) {
+                    return Err("Wrong passphrase".to_string());
+                }
+                inner.key = Some(key);
+            }
+            Some(KeySource::Keyring) => {
+                return Err("This vault is unlocked by the OS keyring; reset it if the keyring entry is lost".to_string())
+            }
+            None => {
+                let mut salt = [0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                let params = Params::default();
+                let (m_cost, t_cost, p_cost) = (params.m_cost(), params.t_cost(), params.p_cost());
+                let key = derive_key(passphrase, &salt, m_cost, t_cost, p_cost)?;
+                let source = KeySource::Passphrase { salt: encode(&salt), m_cost, t_cost, p_cost };
+                let file = new_file(source, &key)?;
+                self.write(&file)?;
+                inner.file = Some(file);
+                inner.key = Some(key);
+            }
+        }
+        println!("[Vault] ✓ Unlocked with passphrase");
+        Ok(())
+    }
+
+    /// Start over with an empty vault, for when it can't be unlocked: the
+    /// keyring entry is gone or no longer matches, or the passphrase is
+    /// forgotten. The old file is kept as `vault-<time>.json.bak`; its
+    /// credentials have to be entered again. An unlocked vault is only reset
+    /// with `force`.
+    pub fn reset(&self, force: bool) -> Result<(), String> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.key.is_some() && !force {
+            return Err("The vault is unlocked; resetting it would lose its credentials".to_string());
+        }
+        if self.path.exists() {
+            let backup = self.path.with_file_name(format!("vault-{}.json.bak", now_ms()));
+            std::fs::rename(&self.path, &backup)
+                .map_err(|e| format!("Failed to move {} aside: {}", self.path.display(), e))?;
+            println!("[Vault] Moved the old vault to {}", backup.display());
+        }
+        inner.file = None;
+        inner.key = None;
+        match self.create_keyring_vault() {
+            Ok((file, key)) => {
+                self.write(&file)?;
+                inner.file = Some(file);
+                inner.key = Some(key);
+                println!("[Vault] ✓ Created vault with a keyring key");
+            }
+            Err(e) => eprintln!("[Vault] No keyring ({}); a passphrase is required", e),
+        }
+        Ok(())
+    }
+
+    pub fn set(&self, name: &str, secret: &str) -> Result<(), String> {
+        let secret = secret.trim();
+        if secret.is_empty() {
+            return Err("Credential is empty".to_string());
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let key = inner.key.ok_or("Vault is locked")?;
+        let mut file = inner.file.clone().ok_or("Vault is locked")?;
+        let tail: String = secret.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+        file.credentials.insert(
+            name.to_string(),
+            StoredCredential { sealed: seal(&key, name, secret.as_bytes())?, hint: format!("…{}", tail), updated_at: now_ms() },
+        );
+        self.write(&file)?;
+        inner.file = Some(file);
+        println!("[Vault] Stored credential '{}'", name);
+        Ok(())
+    }
+
+    /// Decrypted credential for backend use only; never hand this to the frontend.
+    pub fn get(&self, name: &str) -> Result<Option<String>, String> {
+        let inner = self.inner.lock().unwrap();
+        let Some(stored) = inner.file.as_ref().and_then(|f| f.credentials.get(name)) else { return Ok(None) };
+        let key = inner.key.ok_or("Vault is locked")?;
+        let plain = open_sealed(&key, name, &stored.sealed)?;
+        String::from_utf8(plain).map(Some).map_err(|_| format!("Credential '{}' is corrupt", name))
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(mut file) = inner.file.clone() else { return Ok(()) };
+        if file.credentials.remove(name).is_some() {
+            self.write(&file)?;
+            inner.file = Some(file);
+            println!("[Vault] Deleted credential '{}'", name);
+        }
+        Ok(())
+    }
+
+    fn keyring_entry(&self) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(&self.service, KEYRING_USER).map_err(|e| e.to_string())
+    }
+
+    fn keyring_key(&self) -> Result<[u8; 32], String> {
+        let encoded = self.keyring_entry()?.get_password().map_err(|e| e.to_string())?;
+        decode(&encoded)?.try_into().map_err(|_| "keyring key has the wrong length".to_string())
+    }
+
+    fn create_keyring_vault(&self) -> Result<(VaultFile, [u8; 32]), String> {
+        let key: [u8; 32] = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+        self.keyring_entry()?.set_password(&encode(&key)).map_err(|e| e.to_string())?;
+        // Some backends accept writes but can't read them back (no daemon, locked collection).
+        if self.keyring_key()? != key {
+            return Err("keyring did not return the stored key".to_string());
+        }
+        Ok((new_file(KeySource::Keyring, &key)?, key))
+    }
+
+    fn write(&self, file: &VaultFile) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(file).map_err(|e| format!("Failed to serialize vault: {}", e))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+/// Move a plaintext `api_key` left in the settings store by older versions
+/// into the vault, under the provider it was used with.
+pub fn migrate_plaintext_key(app: &AppHandle) {
+    let Some(api_key) = settings::get::<String>(app, "api_key").filter(|k| !k.trim().is_empty()) else { return };
+    let vault = app.state::<Vault>();
+    if !vault.status().unlocked {
+        return;
+    }
+    let provider: String = settings::get(app, "llm_provider").unwrap_or_else(|| "gemini".to_string());
+    match vault.set(&provider, &api_key).and_then(|_| settings::remove(app, "api_key")) {
+        Ok(()) => println!("[Vault] ✓ Moved the plaintext API key into the vault"),
+        Err(e) => eprintln!("[Vault] ✗ Failed to migrate the plaintext API key: {}", e),
+    }
+}
+
+fn new_file(key_source: KeySource, key: &[u8; 32]) -> Result<VaultFile, String> {
+    Ok(VaultFile { version: 1, key_source, check: seal(key, "check", CHECK_MARKER)?, credentials: BTreeMap::new() })
+}
+
+fn verify(key: &[u8; 32], file: &VaultFile) -> bool {
+    open_sealed(key, "check", &file.check).is_ok_and(|plain| plain == CHECK_MARKER)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], m_cost: u32, t_cost: u32, p_cost: u32) -> Result<[u8; 32], String> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32)).map_err(|e| format!("Invalid KDF parameters: {}", e))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypt with a fresh random nonce; `name` is bound as associated data so
+/// ciphertexts can't be swapped between entries.
+fn seal(key: &[u8; 32], name: &str, plain: &[u8]) -> Result<Sealed, String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plain, aad: name.as_bytes() })
+        .map_err(|_| "Encryption failed".to_string())?;
+    Ok(Sealed { nonce: encode(&nonce), ciphertext: encode(&ciphertext) })
+}
+
+fn open_sealed(key: &[u8; 32], name: &str, sealed: &Sealed) -> Result<Vec<u8>, String> {
+    let nonce = decode(&sealed.nonce)?;
+    if nonce.len() != 12 {
+        return Err("Invalid nonce".to_string());
+    }
+    let ciphertext = decode(&sealed.ciphertext)?;
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: name.as_bytes() })
+        .map_err(|_| format!("Failed to decrypt '{}'", name))
+}
+
+fn encode(bytes: &[u8]) -> String {
+    general_purpose::STANDARD.encode(bytes)
+}
+
+fn decode(text: &str) -> Result<Vec<u8>, String> {
+    general_purpose::STANDARD.decode(text).map_err(|e| format!("Invalid base64 in vault: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A vault file in a fresh directory, unlocked or not. The mock keyring
+    /// can't read back what it stores, so a reset leaves the vault empty
+    /// and waiting for a passphrase.
+    fn vault(name: &str, unlocked: bool) -> Vault {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        let dir = std::env::temp_dir().join(format!("vault-test-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key: [u8; 32] = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+        let vault = Vault {
+            path: dir.join(VAULT_FILE),
+            service: "vault-test".to_string(),
+            inner: Mutex::new(Inner { file: None, key: None }),
+        };
+        let file = new_file(KeySource::Keyring, &key).unwrap();
+        vault.write(&file).unwrap();
+        *vault.inner.lock().unwrap() = Inner { file: Some(file), key: unlocked.then_some(key) };
+        vault
+    }
+
+    fn backups(vault: &Vault) -> usize {
+        std::fs::read_dir(vault.path.parent().unwrap())
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().file_name().to_string_lossy().ends_with(".json.bak"))
+            .count()
+    }
+
+    #[test]
+    fn unlocked_vault_is_only_reset_with_force() {
+        let vault = vault("unlocked", true);
+        assert!(vault.reset(false).is_err());
+        assert!(vault.path.exists());
+        assert!(vault.status().unlocked);
+        assert_eq!(backups(&vault), 0);
+
+        vault.reset(true).unwrap();
+        assert!(!vault.path.exists());
+        assert_eq!(backups(&vault), 1);
+        assert_eq!(vault.status().mode, "none");
+        std::fs::remove_dir_all(vault.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn locked_vault_is_reset_and_kept_as_a_backup() {
+        let vault = vault("locked", false);
+        vault.reset(false).unwrap();
+        assert!(!vault.path.exists());
+        assert_eq!(backups(&vault), 1);
+        let status = vault.status();
+        assert!(!status.unlocked && status.needs_passphrase);
+        std::fs::remove_dir_all(vault.path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,8 +1,7 @@
 import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { fetch } from "@tauri-apps/plugin-http";
 import { load } from "@tauri-apps/plugin-store";
-import { listen } from "@tauri-apps/api/event";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import {
   Settings as SettingsIcon, MessageSquare,
   Send, Cpu, Eye, EyeOff, RefreshCw,
@@ -18,10 +17,58 @@ interface ChatMessage { role: "user" | "model" | "system"; content: string; }
 interface AudioPayload { speaker: string; data: string; amplitude: number; }
 interface DeviceInfo { name: string; is_input: boolean; }
 interface VoiceHint { id: number; text: string; }
+interface ModelInfo { id: string; display_name: string; }
+interface OcrResult { text: string; elapsed_ms: number; }
+interface Screenshot { data: string; mime_type: string; width: number; height: number; bytes: number; ocr: OcrResult | null; prefer_text: boolean; masked: boolean | null; }
+interface Region { x: number; y: number; width: number; height: number; }
+interface DisplayInfo { id: number; x: number; y: number; width: number; height: number; scale_factor: number; is_primary: boolean; }
+interface LlmResponse { provider: string; model: string; text: string; }
+interface CredentialInfo { name: string; hint: string; updated_at: number; }
+interface VaultStatus { mode: string; unlocked: boolean; needs_passphrase: boolean; credentials: CredentialInfo[]; }
+interface RedactionStatus { enabled: boolean; screenshot_masking: boolean; }
+interface LlmDelta { request_id: string; delta: string; }
+interface LlmDone { request_id: string; response: LlmResponse; }
+interface LlmFailure { request_id: string; error: string; }
+interface BudgetStatus { period: string; spent: number; soft: number | null; hard: number | null; }
+interface BudgetEvent { level: "soft" | "hard"; status: BudgetStatus; }
+interface HotkeyEvent { action: "toggle_capture" | "screenshot" | "capture_region" | "send_context"; }
 
 const STORE_PATH = "settings.dat";
 let hintIdCounter = 0;
 
+// ── Streaming LLM request: relays llm-delta events until llm-done / llm-error ──
+const streamLlm = async (
+  request: object,
+  onDelta: (delta: string) => void,
+  signal?: AbortSignal
+): Promise<LlmResponse> => {
+  const requestId = crypto.randomUUID();
+  let resolve!: (r: LlmResponse) => void;
+  let reject!: (e: Error) => void;
+  const finished = new Promise<LlmResponse>((res, rej) => { resolve = res; reject = rej; });
+
+  const unlisten: UnlistenFn[] = await Promise.all([
+    listen<LlmDelta>("llm-delta", e => { if (e.payload.request_id === requestId) onDelta(e.payload.delta); }),
+    listen<LlmDone>("llm-done", e => { if (e.payload.request_id === requestId) resolve(e.payload.response); }),
+    listen<LlmFailure>("llm-error", e => { if (e.payload.request_id === requestId) reject(new Error(e.payload.error)); }),
+  ]);
+  const onAbort = () => {
+    invoke("llm_cancel", { requestId }).catch(console.error);
+    const err = new Error("Aborted");
+    err.name = "AbortError";
+    reject(err);
+  };
+  signal?.addEventListener("abort", onAbort);
+
+  try {
+    await invoke("llm_stream", { requestId, request });
+    return await finished;
+  } finally {
+    signal?.removeEventListener("abort", onAbort);
+    unlisten.forEach(u => u());
+  }
+};
+
 const tabTransition = {
   initial: { opacity: 0, y: 8 },
   animate: { opacity: 1, y: 0 },
@@ -30,8 +77,14 @@ const tabTransition = {
 };
 
 function App() {
-  const [apiKey, setApiKey] = useState("");
-  const [models, setModels] = useState<any[]>([]);
+  const [vault, setVault] = useState<VaultStatus | null>(null);
+  const [redaction, setRedaction] = useState<RedactionStatus | null>(null);
+  const [keyInput, setKeyInput] = useState("");
+  const [passphrase, setPassphrase] = useState("");
+  const [keyStatus, setKeyStatus] = useState("");
+  const [provider, setProvider] = useState("gemini");
+  const [baseUrl, setBaseUrl] = useState("");
+  const [models, setModels] = useState<ModelInfo[]>([]);
   const [selectedModel, setSelectedModel] = useState("models/gemini-1.5-flash");
   const [activeTab, setActiveTab] = useState<"chat" | "settings" | "voice">("chat");
   const [messages, setMessages] = useState<ChatMessage[]>([]);
@@ -47,6 +100,8 @@ function App() {
   const [interAmp, setInterAmp] = useState(0);
   const [devices, setDevices] = useState<DeviceInfo[]>([]);
   const [interviewerDevice, setInterviewerDevice] = useState<string>("");
+  const [displays, setDisplays] = useState<DisplayInfo[]>([]);
+  const [screenshotDisplay, setScreenshotDisplay] = useState<string>("primary");
 
   const scrollRef = useRef<HTMLDivElement>(null);
   const abortRef = useRef<AbortController | null>(null);
@@ -59,95 +114,22 @@ function App() {
     setIsLoading(false);
   };
 
+  // Local / self-hosted providers may not need a key
+  const storedKey = vault?.credentials.find(c => c.name === provider);
+  const isConfigured = provider !== "gemini" || !!storedKey;
+
   // Returns tooltip text only when Ghost Mode is off
   const tip = (text: string) => isProtected ? undefined : text;
 
-  // ── SSE Stream helper ──
-  const streamSSE = async (
-    url: string,
-    body: any,
-    onChunk: (text: string) => void,
-    signal?: AbortSignal
-  ): Promise<void> => {
-    const r = await fetch(url, {
-      method: "POST",
-      headers: { "Content-Type": "application/json", "x-goog-api-key": apiKey },
-      body: JSON.stringify(body),
-      signal,
-    });
-    if (!r.ok) {
-      const errText = await r.text();
-      throw new Error(`API Error ${r.status}: ${errText}`);
-    }
-
-    const reader = r.body?.getReader();
-    if (!reader) throw new Error("No response body");
-    const decoder = new TextDecoder();
-    let buffer = "";
-
-    while (true) {
-      const { done, value } = await reader.read();
-      if (done) break;
-      buffer += decoder.decode(value, { stream: true });
-
-      // Parse SSE events from buffer
-      const lines = buffer.split("\n");
-      buffer = lines.pop() || ""; // keep incomplete line
-
-      for (const line of lines) {
-        const trimmed = line.trim();
-        if (!trimmed.startsWith("data: ")) continue;
-        const jsonStr = trimmed.slice(6);
-        if (!jsonStr || jsonStr === "[DONE]") continue;
-        try {
-          const parsed = JSON.parse(jsonStr);
-          const chunk = parsed.candidates?.[0]?.content?.parts?.[0]?.text;
-          if (chunk) onChunk(chunk);
-        } catch { /* skip malformed JSON */ }
-      }
-    }
-  };
-
   useEffect(() => {
     const unlisten = listen<AudioPayload>("audio-chunk", async (event) => {
       const { speaker, data, amplitude } = event.payload;
       // Only process interviewer audio — user mic is not captured
       if (speaker !== "interviewer") return;
       setInterAmp(amplitude);
-      if (!isVoiceActive || !apiKey || !data) return;
+      if (!isVoiceActive || !isConfigured || !data) return;
 
       try {
-        const url = `https://generativelanguage.googleapis.com/v1beta/${selectedModel}:streamGenerateContent?alt=sse`;
-        const prompt = `Ты senior-инженер, помогаешь кандидату на техническом собеседовании. Тебе дан аудиофрагмент голоса интервьюера.
-
-ПЕРВЫМ ДЕЛОМ определи: содержит ли аудио ВОПРОС или ЗАДАНИЕ кандидату?
-- Вопрос: прямой вопрос, просьба объяснить, задача, "расскажите о...", "как бы вы...", "что такое...", просьба написать код
-- НЕ вопрос: приветствие, small talk, комментарии, переходные фразы, тишина, шум, "давайте перейдём к...", "хорошо", "понятно"
-
-Если это НЕ ВОПРОС — ответь ТОЛЬКО одним словом: SKIP
-
-Если это ВОПРОС — дай РАЗВЁРНУТЫЙ и КОНКРЕТНЫЙ ответ, который кандидат может пересказать своими словами:
-
-**Суть**: одно предложение — что спрашивают
-**Ответ**: подробный ответ на 12-15 предложений. Структурируй по блокам если это system design:
-- **Компоненты**: какие сервисы, БД, кэши, очереди нужны и почему именно они
-- **Потоки данных**: как данные проходят через систему, write path и read path
-- **Хранение**: схема данных, выбор БД (SQL vs NoSQL), индексы, партиционирование
-- **Масштабирование**: шардирование, репликация, load balancing, CDN
-- **Надёжность**: отказоустойчивость, graceful degradation, мониторинг
-- **Расчёты**: примерные QPS, объём данных, latency requirements
-Используй конкретные технологии, паттерны, цифры, trade-offs. Объясняй КАК и ПОЧЕМУ.
-Если это вопрос по архитектуре ПО — раскрой:
-- **Паттерны**: CQRS, Event Sourcing, Saga, Circuit Breaker, Strangler Fig, Outbox, etc. — когда применять и почему
-- **Принципы**: SOLID, DDD (bounded contexts, aggregates, domain events), Clean/Hexagonal Architecture, разделение слоёв
-- **Коммуникация сервисов**: sync (REST, gRPC) vs async (Kafka, RabbitMQ, SQS), choreography vs orchestration, idempotency
-- **Обработка ошибок**: retry с exponential backoff, dead letter queues, compensating transactions, eventual consistency
-- **Observability**: distributed tracing (Jaeger/Zipkin), structured logging, метрики (RED/USE), alerting
-- **Тестирование**: contract tests, integration tests, chaos engineering
-Если это алгоритмическая задача — опиши подход пошагово, сложность O(), структуры данных, edge cases.
-
-Отвечай на русском. Давай ответ такой глубины, какой ожидают от senior/staff кандидата.`;
-
         // Each hint gets a unique ID so parallel streams don't conflict
         const hintId = ++hintIdCounter;
         let accumulated = "";
@@ -156,54 +138,101 @@ function App() {
         // Add placeholder with unique ID
         setSuggestions(prev => [{ id: hintId, text: "⏳ Слушаю..." }, ...prev].slice(0, 10));
 
-        await streamSSE(
-          url,
-          {
-            contents: [{
-              parts: [
-                { text: prompt },
-                { inlineData: { mimeType: "audio/wav", data: data } }
-              ]
-            }]
-          },
-          (chunk) => {
-            accumulated += chunk;
-            // Check if AI decided this is not a question
-            const trimmed = accumulated.trim();
-            if (trimmed === "SKIP" || trimmed === "SKIP." || trimmed.startsWith("SKIP\n") || trimmed.startsWith("SKIP ")) {
-              isSkip = true;
-              // Remove this specific hint by ID
-              setSuggestions(prev => prev.filter(h => h.id !== hintId));
-              return;
+        try {
+          await streamLlm(
+            {
+              kind: "hint",
+              // Only the newest queued hint is worth waiting for
+              group: "hint",
+              model: selectedModel,
+              template: "hint",
+              parts: [{ type: "audio", mime_type: "audio/wav", data }]
+            },
+            (chunk) => {
+              accumulated += chunk;
+              // Check if AI decided this is not a question
+              const trimmed = accumulated.trim();
+              if (trimmed === "SKIP" || trimmed === "SKIP." || trimmed.startsWith("SKIP\n") || trimmed.startsWith("SKIP ")) {
+                isSkip = true;
+                // Remove this specific hint by ID
+                setSuggestions(prev => prev.filter(h => h.id !== hintId));
+                return;
+              }
+              if (isSkip) return;
+              // Update only this specific hint by ID
+              setSuggestions(prev =>
+                prev.map(h => h.id === hintId ? { ...h, text: accumulated } : h)
+              );
             }
-            if (isSkip) return;
-            // Update only this specific hint by ID
-            setSuggestions(prev =>
-              prev.map(h => h.id === hintId ? { ...h, text: accumulated } : h)
-            );
+          );
+        } finally {
+          // Remove if empty, skipped or failed
+          if (!accumulated || isSkip) {
+            setSuggestions(prev => prev.filter(h => h.id !== hintId));
           }
-        );
-
-        // Remove if empty or skipped
-        if (!accumulated || isSkip) {
-          setSuggestions(prev => prev.filter(h => h.id !== hintId));
         }
       } catch (e) { console.error(e); }
     });
     return () => { unlisten.then(f => f()); };
-  }, [isVoiceActive, apiKey, selectedModel]);
+  }, [isVoiceActive, isConfigured, selectedModel]);
+
+  // Budget warnings from the backend usage tracker
+  useEffect(() => {
+    const unlisten = listen<BudgetEvent>("llm-budget", ({ payload: { level, status } }) => {
+      const limit = level === "hard" ? status.hard : status.soft;
+      const note = level === "hard" ? "New requests are blocked." : "Requests continue until the hard limit.";
+      setMessages(prev => [...prev, {
+        role: "system",
+        content: `⚠️ ${level === "hard" ? "Hard" : "Soft"} budget reached: $${status.spent.toFixed(2)} of $${limit?.toFixed(2)} this ${status.period}. ${note}`
+      }]);
+    });
+    return () => { unlisten.then(f => f()); };
+  }, []);
+
+  // Global shortcuts; toggling the window is handled by the backend
+  useEffect(() => {
+    const unlisten = listen<HotkeyEvent>("hotkey", async ({ payload: { action } }) => {
+      switch (action) {
+        case "toggle_capture":
+          toggleVoiceMode();
+          break;
+        case "screenshot":
+          captureAndAnalyze();
+          break;
+        case "capture_region": {
+          const region = await (await load(STORE_PATH)).get<Region>("screenshot_region");
+          if (!region) {
+            setMessages(prev => [...prev, { role: "system", content: "⚠️ Set screenshot_region to capture a region." }]);
+            return;
+          }
+          captureAndAnalyze(region);
+          break;
+        }
+        case "send_context":
+          sendMessage(input.trim() ? input : "Помоги ответить на последний вопрос интервьюера.");
+          break;
+      }
+    });
+    return () => { unlisten.then(f => f()); };
+  }, [isVoiceActive, isConfigured, isLoading, selectedModel, interviewerDevice, input]);
 
   useEffect(() => {
     load(STORE_PATH).then(async s => {
-      const k = await s.get<string>("api_key");
+      const p = await s.get<string>("llm_provider");
       const m = await s.get<string>("selected_model");
       const id = await s.get<string>("interviewer_device");
       const pinned = await s.get<boolean>("always_on_top");
       const prot = await s.get<boolean>("screen_protection");
+      const sd = await s.get<string | number>("screenshot_display");
 
-      if (k) { setApiKey(k); fetchModels(k); }
+      if (p) setProvider(p);
+      const u = await s.get<string>(`${p ?? "gemini"}_base_url`);
+      if (u) setBaseUrl(u);
+      await refreshVault();
+      fetchModels();
       if (m) setSelectedModel(m);
       if (id) setInterviewerDevice(id || "");
+      if (sd !== undefined && sd !== null) setScreenshotDisplay(String(sd));
       if (pinned !== undefined) {
         setIsAlwaysOnTop(pinned);
         invoke("set_always_on_top", { enabled: pinned });
@@ -213,20 +242,77 @@ function App() {
         invoke("toggle_screen_share_protection", { enabled: prot });
       }
       refreshDevices();
+      invoke<RedactionStatus>("get_redaction_status").then(setRedaction).catch(console.error);
     });
   }, []);
 
   const refreshDevices = async () => {
     const devs = await invoke<DeviceInfo[]>("get_audio_devices");
     setDevices(devs);
+    setDisplays(await invoke<DisplayInfo[]>("list_displays"));
   };
 
-  const fetchModels = async (key: string) => {
+  const fetchModels = async () => {
     try {
-      const r = await fetch(`https://generativelanguage.googleapis.com/v1beta/models?key=${key}`);
-      const d = await r.json();
-      if (d.models) setModels(d.models.filter((m: any) => m.supportedGenerationMethods.includes("generateContent")));
-    } catch (e) { }
+      setModels(await invoke<ModelInfo[]>("llm_list_models"));
+    } catch (e) { setModels([]); console.error(e); }
+  };
+
+  const refreshVault = async () => {
+    try {
+      setVault(await invoke<VaultStatus>("vault_status"));
+    } catch (e) { console.error(e); }
+  };
+
+  const resetVault = async () => {
+    if (!confirm("Reset the vault? Saved API keys will have to be entered again.")) return;
+    try {
+      setVault(await invoke<VaultStatus>("reset_vault"));
+      setKeyStatus("");
+    } catch (e) { setKeyStatus(`❌ ${e}`); }
+  };
+
+  const unlockVault = async () => {
+    try {
+      setVault(await invoke<VaultStatus>("vault_unlock", { passphrase }));
+      setPassphrase("");
+      setKeyStatus("");
+      fetchModels();
+    } catch (e) { setKeyStatus(`❌ ${e}`); }
+  };
+
+  // Keys are write-only: the backend keeps them encrypted and only reports a hint
+  const saveKey = async () => {
+    if (!keyInput.trim()) return;
+    try {
+      setVault(await invoke<VaultStatus>("set_credential", { name: provider, secret: keyInput }));
+      setKeyInput("");
+      setKeyStatus("Saved");
+      fetchModels();
+    } catch (e) { setKeyStatus(`❌ ${e}`); }
+  };
+
+  const testKey = async () => {
+    setKeyStatus("Testing...");
+    try {
+      const count = await invoke<number>("test_credential", { name: provider });
+      setKeyStatus(`✓ Works (${count} models)`);
+    } catch (e) { setKeyStatus(`❌ ${e}`); }
+  };
+
+  const deleteKey = async () => {
+    try {
+      setVault(await invoke<VaultStatus>("delete_credential", { name: provider }));
+      setKeyStatus("Deleted");
+      setModels([]);
+    } catch (e) { setKeyStatus(`❌ ${e}`); }
+  };
+
+  const changeProvider = async (p: string) => {
+    setProvider(p);
+    await saveSettings("llm_provider", p);
+    setBaseUrl(await (await load(STORE_PATH)).get<string>(`${p}_base_url`) ?? "");
+    fetchModels();
   };
 
   const toggleVoiceMode = async () => {
@@ -234,7 +320,8 @@ function App() {
     setIsVoiceActive(newState);
     if (newState) {
       await invoke("start_interview_mode", {
-        interviewerDevice: interviewerDevice || null
+        interviewerDevice: interviewerDevice || null,
+        settings: { model: selectedModel }
       });
       setActiveTab("voice");
     }
@@ -261,40 +348,39 @@ function App() {
     await s.save();
   };
 
-  const sendMessage = async () => {
-    if (!input.trim() || !apiKey || isLoading) return;
-    const text = input;
+  // Append a streamed chunk to the model message at the end of the chat
+  const appendToLastReply = (chunk: string) => {
+    setMessages(prev => {
+      if (prev.length === 0) return prev;
+      const next = [...prev];
+      const last = next[next.length - 1];
+      if (!last || last.role !== "model") return prev;
+      next[next.length - 1] = { ...last, content: last.content + chunk };
+      return next;
+    });
+
+    if (scrollRef.current) {
+      scrollRef.current.scrollTop = scrollRef.current.scrollHeight;
+    }
+  };
+
+  const sendMessage = async (text = input) => {
+    if (!text.trim() || !isConfigured || isLoading) return;
     setMessages(prev => [...prev, { role: "user", content: text }]);
     setInput("");
     setIsLoading(true);
 
     try {
-      const url = `https://generativelanguage.googleapis.com/v1beta/${selectedModel}:streamGenerateContent?alt=sse`;
       abortRef.current = new AbortController();
 
       // Add empty model message
       setMessages(prev => [...prev, { role: "model", content: "" }]);
 
-      await streamSSE(
-        url,
-        { contents: [{ parts: [{ text }] }] },
-        (chunk) => {
-          setMessages(prev => {
-            if (prev.length === 0) return prev;
-            const next = [...prev];
-            const last = next[next.length - 1];
-            if (!last || last.role !== "model") return prev;
-            next[next.length - 1] = { ...last, content: last.content + chunk };
-            return next;
-          });
-
-          if (scrollRef.current) {
-            scrollRef.current.scrollTop = scrollRef.current.scrollHeight;
-          }
-        },
+      await streamLlm(
+        { kind: "chat", model: selectedModel, template: "chat", variables: { message: text } },
+        appendToLastReply,
         abortRef.current.signal
       );
-
       setIsLoading(false);
     } catch (e) {
       if (e instanceof Error && e.name === "AbortError") {
@@ -307,50 +393,44 @@ function App() {
     }
   };
 
-  const captureAndAnalyze = async () => {
-    if (!apiKey || isLoading) return;
+  const captureAndAnalyze = async (region?: Region) => {
+    if (!isConfigured || isLoading) return;
     setIsLoading(true);
 
     try {
-      const screenshotBase64 = await invoke<string>("capture_screenshot");
+      const screenshot = await invoke<Screenshot>("capture_screenshot", { region: region ?? null });
 
-      setMessages(prev => [...prev, { role: "user", content: "📷 [Screenshot captured]" }]);
+      // Send the OCR text alone when the backend says it's cheaper; pass it
+      // explicitly either way so an older screenshot's text isn't picked up.
+      const screenshotText = screenshot.ocr?.text ?? "";
+      const parts = screenshot.prefer_text
+        ? []
+        : [{ type: "image", mime_type: screenshot.mime_type, data: screenshot.data }];
 
-      const url = `https://generativelanguage.googleapis.com/v1beta/${selectedModel}:streamGenerateContent?alt=sse`;
-      const prompt = "Проанализируй этот скриншот. Если видишь задачу, код, вопрос или проблему - помоги решить, объясни или дай рекомендации на русском языке.";
+      if (screenshot.masked === false) {
+        setMessages(prev => [...prev, { role: "system", content: "⚠️ Screenshot was not redacted: text recognition failed." }]);
+      }
+      setMessages(prev => [...prev, {
+        role: "user",
+        content: screenshot.prefer_text ? "📷 [Screenshot captured, sent as text]" : "📷 [Screenshot captured]"
+      }]);
 
       abortRef.current = new AbortController();
 
       // Add empty model message
       setMessages(prev => [...prev, { role: "model", content: "" }]);
 
-      await streamSSE(
-        url,
+      await streamLlm(
         {
-          contents: [{
-            parts: [
-              { text: prompt },
-              { inlineData: { mimeType: "image/png", data: screenshotBase64 } }
-            ]
-          }]
-        },
-        (chunk) => {
-          setMessages(prev => {
-            if (prev.length === 0) return prev;
-            const next = [...prev];
-            const last = next[next.length - 1];
-            if (!last || last.role !== "model") return prev;
-            next[next.length - 1] = { ...last, content: last.content + chunk };
-            return next;
-          });
-
-          if (scrollRef.current) {
-            scrollRef.current.scrollTop = scrollRef.current.scrollHeight;
-          }
+          kind: "screenshot",
+          model: selectedModel,
+          template: "screenshot",
+          variables: { screenshot_text: screenshotText },
+          parts
         },
+        appendToLastReply,
         abortRef.current.signal
       );
-
       setIsLoading(false);
     } catch (e) {
       if (e instanceof Error && e.name === "AbortError") {
@@ -428,10 +508,10 @@ function App() {
           </button>
           <button
             id="btn-screenshot"
-            onClick={captureAndAnalyze}
+            onClick={() => captureAndAnalyze()}
             className={`btn-icon-m3 ${isLoading ? "active" : ""}`}
             title={tip("Capture screenshot & analyze")}
-            disabled={isLoading || !apiKey}
+            disabled={isLoading || !isConfigured}
           >
             <Camera size={16} />
           </button>
@@ -516,7 +596,7 @@ function App() {
                     <Square size={14} />
                   </button>
                 ) : (
-                  <button id="btn-send" onClick={sendMessage} className="btn-send-m3">
+                  <button id="btn-send" onClick={() => sendMessage()} className="btn-send-m3">
                     <Send size={16} />
                   </button>
                 )}
@@ -573,6 +653,35 @@ function App() {
             <motion.div key="settings" className="settings-container" {...tabTransition}>
               <h2 className="settings-title">Settings</h2>
 
+              {/* Provider */}
+              <div className="settings-card-m3">
+                <span className="m3-label">Provider</span>
+                <div className="select-wrapper">
+                  <select
+                    id="select-provider"
+                    value={provider}
+                    onChange={e => changeProvider(e.target.value)}
+                    className="m3-input-text"
+                  >
+                    <option value="gemini">Google Gemini</option>
+                    <option value="openai">OpenAI-compatible</option>
+                    <option value="ollama">Ollama (local)</option>
+                  </select>
+                  <ChevronDown size={14} className="select-arrow" />
+                </div>
+                {provider !== "gemini" && (
+                  <input
+                    id="input-base-url"
+                    type="text"
+                    value={baseUrl}
+                    placeholder={provider === "ollama" ? "http://localhost:11434" : "https://api.openai.com/v1"}
+                    onChange={e => { setBaseUrl(e.target.value); saveSettings(`${provider}_base_url`, e.target.value); }}
+                    onBlur={fetchModels}
+                    className="m3-input-text"
+                  />
+                )}
+              </div>
+
               {/* Model selector */}
               <div className="settings-card-m3">
                 <span className="m3-label">Selected AI Model</span>
@@ -584,9 +693,11 @@ function App() {
                     className="m3-input-text"
                   >
                     {models.length > 0 ? (
-                      models.map(m => <option key={m.name} value={m.name}>{m.displayName || m.name}</option>)
-                    ) : (
+                      models.map(m => <option key={m.id} value={m.id}>{m.display_name}</option>)
+                    ) : provider === "gemini" ? (
                       <option value="models/gemini-1.5-flash">Gemini 1.5 Flash (Default)</option>
+                    ) : (
+                      <option value={selectedModel}>No models found</option>
                     )}
                   </select>
                   <ChevronDown size={14} className="select-arrow" />
@@ -594,21 +705,58 @@ function App() {
               </div>
 
               {/* API Key */}
-              <div className="settings-card-m3">
+              {provider !== "ollama" && <div className="settings-card-m3">
                 <span className="m3-label">API Key</span>
-                <div className="api-key-wrapper">
-                  <input
-                    id="input-api-key"
-                    type={isKeyVisible ? "text" : "password"}
-                    value={apiKey}
-                    onChange={e => { setApiKey(e.target.value); saveSettings("api_key", e.target.value); }}
-                    className="m3-input-text"
-                  />
-                  <button onClick={() => setIsKeyVisible(!isKeyVisible)} className="api-key-toggle">
-                    {isKeyVisible ? <EyeOff size={16} /> : <Eye size={16} />}
-                  </button>
-                </div>
-              </div>
+                {vault && !vault.unlocked && vault.mode === "keyring" ? (
+                  <>
+                    <div className="settings-toggle-desc">
+                      The system keyring no longer holds the key to your saved keys — reset the vault to start over
+                    </div>
+                    <button id="btn-reset-vault" onClick={resetVault} className="btn-refresh">Reset vault</button>
+                  </>
+                ) : vault && !vault.unlocked ? (
+                  <>
+                    <div className="settings-toggle-desc">
+                      {vault.needs_passphrase
+                        ? "No system keyring available — choose a passphrase to encrypt your keys"
+                        : "Keys are locked — enter your passphrase"}
+                    </div>
+                    <input
+                      id="input-vault-passphrase"
+                      type="password"
+                      value={passphrase}
+                      onChange={e => setPassphrase(e.target.value)}
+                      onKeyDown={e => { if (e.key === "Enter") unlockVault(); }}
+                      className="m3-input-text"
+                    />
+                    <button id="btn-unlock-vault" onClick={unlockVault} className="btn-refresh">Unlock</button>
+                    {vault.mode === "passphrase" && (
+                      <button id="btn-reset-vault" onClick={resetVault} className="btn-refresh">Forgot passphrase? Reset vault</button>
+                    )}
+                  </>
+                ) : (
+                  <>
+                    <div className="api-key-wrapper">
+                      <input
+                        id="input-api-key"
+                        type={isKeyVisible ? "text" : "password"}
+                        value={keyInput}
+                        placeholder={storedKey ? `Saved ${storedKey.hint}` : "Paste API key"}
+                        onChange={e => setKeyInput(e.target.value)}
+                        onKeyDown={e => { if (e.key === "Enter") saveKey(); }}
+                        className="m3-input-text"
+                      />
+                      <button onClick={() => setIsKeyVisible(!isKeyVisible)} className="api-key-toggle">
+                        {isKeyVisible ? <EyeOff size={16} /> : <Eye size={16} />}
+                      </button>
+                    </div>
+                    <button id="btn-save-key" onClick={saveKey} disabled={!keyInput.trim()} className="btn-refresh">Save</button>{" "}
+                    <button id="btn-test-key" onClick={testKey} disabled={!storedKey} className="btn-refresh">Test</button>{" "}
+                    <button id="btn-delete-key" onClick={deleteKey} disabled={!storedKey} className="btn-refresh">Delete</button>
+                  </>
+                )}
+                {keyStatus && <div className="settings-toggle-desc">{keyStatus}</div>}
+              </div>}
 
 
 
@@ -646,6 +794,40 @@ function App() {
                 </select>
               </div>
 
+              {/* Screenshot display */}
+              <div className="settings-card-m3">
+                <span className="m3-label">Screenshot Display</span>
+                <select
+                  id="select-screenshot-display"
+                  value={screenshotDisplay}
+                  onChange={e => {
+                    const v = e.target.value;
+                    setScreenshotDisplay(v);
+                    // Display IDs are stored as numbers, modes as strings
+                    saveSettings("screenshot_display", /^\d+$/.test(v) ? Number(v) : v);
+                  }}
+                  className="m3-input-text"
+                >
+                  <option value="primary">Primary display</option>
+                  <option value="cursor">Display under the cursor</option>
+                  <option value="all">All displays (stitched)</option>
+                  {displays.length > 1 && (
+                    <optgroup label="Displays">
+                      {displays.map((d, i) => (
+                        <option key={d.id} value={String(d.id)}>
+                          {`Display ${i + 1} — ${d.width}×${d.height}${d.is_primary ? " (primary)" : ""}`}
+                        </option>
+                      ))}
+                    </optgroup>
+                  )}
+                </select>
+                {redaction?.enabled && !redaction.screenshot_masking && (
+                  <div className="settings-toggle-desc">
+                    Screenshots aren't redacted: text recognition isn't available in this build or its models are missing
+                  </div>
+                )}
+              </div>
+
               {/* Ghost mode toggle */}
               <div className="settings-card-m3 settings-toggle-card">
                 <div className="settings-toggle-info">